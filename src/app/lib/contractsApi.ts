@@ -12,7 +12,8 @@ import {
   Account,
   Transaction,
   Keypair,
-  StrKey
+  StrKey,
+  Asset
 } from '@stellar/stellar-sdk';
 import * as StellarSdk from '@stellar/stellar-sdk';
 
@@ -87,7 +88,9 @@ export function getCurrentNetworkConfig() {
 export enum MarketState {
   Open = 0,
   Closed = 1,
-  Resolved = 2
+  Resolved = 2,
+  Cancelled = 3,
+  Draw = 4
 }
 
 // Helper function to convert market state to readable string
@@ -99,6 +102,10 @@ export function getMarketStateLabel(state: MarketState): string {
       return 'Closed';
     case MarketState.Resolved:
       return 'Resolved';
+    case MarketState.Cancelled:
+      return 'Cancelled';
+    case MarketState.Draw:
+      return 'Draw';
     default:
       return 'Unknown';
   }
@@ -113,6 +120,10 @@ export function getMarketStateStatus(state: MarketState): { label: string; color
       return { label: 'Closed', color: 'yellow' };
     case MarketState.Resolved:
       return { label: 'Resolved', color: 'blue' };
+    case MarketState.Cancelled:
+      return { label: 'Cancelled', color: 'red' };
+    case MarketState.Draw:
+      return { label: 'Draw', color: 'orange' };
     default:
       return { label: 'Unknown', color: 'gray' };
   }
@@ -343,10 +354,15 @@ export async function initializeContract(): Promise<{ success: boolean; error?:
     // Build initialize call - use string directly (same as createMarket pattern)
     // nativeToScVal can handle string addresses directly
     // IMPORTANT: Convert address to proper Address type, not string
+    // The connected wallet both signs as deployer and becomes the owner
+    // Markets created by the factory take bets in native XLM
     const ownerAddress = new Address(userAddress);
+    const tokenAddress = new Address(Asset.native().contractId(getNetworkPassphrase()));
     const initializeCall = factoryContract.call(
       'initialize',
-      ownerAddress.toScVal()
+      ownerAddress.toScVal(),
+      ownerAddress.toScVal(),
+      tokenAddress.toScVal()
     );
     
     // Build initial transaction
@@ -373,7 +389,8 @@ export async function initializeContract(): Promise<{ success: boolean; error?:
     // Check for simulation errors
     if ('error' in simulationResponse && simulationResponse.error) {
       const errorMsg = String(simulationResponse.error);
-      if (errorMsg.includes('already initialized') || errorMsg.includes('UnreachableCodeReached')) {
+      // FactoryError #1 is AlreadyInitialized
+      if (errorMsg.includes('Error(Contract, #1)') || errorMsg.includes('UnreachableCodeReached')) {
         return { 
           success: false, 
           error: 'Contract is already initialized. You can proceed to create markets.' 
@@ -537,7 +554,7 @@ export async function createMarket(
     const factoryContract = new Contract(CONTRACTS.MarketFactory);
     
     // Note: Owner check will be done by the contract itself during simulation
-    // If the caller is not the owner, the simulation will fail with "Not owner" error
+    // If the caller is not the owner, the simulation will fail with a NotOwner error
     console.log('📝 Creating market with caller:', userAddress);
     
     // Convert parameters to ScVal
@@ -572,7 +589,8 @@ export async function createMarket(
       livestreamIdsScVal,
       questionScVal,
       livestreamTitlesScVal,
-      wasmHashScVal
+      wasmHashScVal,
+      nativeToScVal(null) // fee_bps: None keeps the factory's default fee
     );
     
     // Build initial transaction
@@ -619,10 +637,11 @@ export async function createMarket(
       const errorStr = rawResult.result.error;
       console.error('Simulation error:', errorStr);
       
-      if (errorStr.includes('Not owner')) {
+      // FactoryError codes: #2 NotInitialized, #3 NotOwner
+      if (errorStr.includes('Error(Contract, #3)')) {
         return { success: false, error: 'Only the contract owner can create markets.' };
       }
-      if (errorStr.includes('Contract not initialized')) {
+      if (errorStr.includes('Error(Contract, #2)')) {
         return { success: false, error: 'Contract is not initialized.' };
       }
       if (errorStr.includes('ExistingValue') || errorStr.includes('contract already exists')) {
@@ -1142,7 +1161,7 @@ export async function getMarketInfo(marketAddress: string): Promise<MarketInfo>
     
     const data = scValToNative(response.result.retval);
     
-    // Parse the tuple result: (Vec<u64>, String, State, u64, i128, u64)
+    // Parse the tuple result: (Vec<u64>, String, State, u64, i128, u64, i128)
     const livestreamIds = (data[0] as bigint[]).map(id => Number(id));
     const question = data[1] as string;
     const state = Number(data[2]) as MarketState;
@@ -1466,7 +1485,7 @@ export async function claimPayout(marketAddress: string): Promise<string> {
   }
 }
 
-// Transfer contract ownership to a new owner
+// Nominate a new owner; they must call acceptOwnership to take over
 export async function transferOwnership(newOwnerAddress: string): Promise<string> {
   try {
     const userAddress = await getUserPublicKey();
@@ -1516,6 +1535,53 @@ export async function transferOwnership(newOwnerAddress: string): Promise<string
   }
 }
 
+// Accept a pending ownership transfer as the nominated owner
+export async function acceptOwnership(): Promise<string> {
+  try {
+    const userAddress = await getUserPublicKey();
+    const server = getRpcServer();
+    const sourceAccount = await server.getAccount(userAddress);
+    const factoryContract = new Contract(CONTRACTS.MarketFactory);
+    
+    // Build accept_ownership call
+    const callerAddr = new Address(userAddress);
+    const acceptCall = factoryContract.call(
+      'accept_ownership',
+      callerAddr.toScVal() // caller
+    );
+    
+    const tx = new TransactionBuilder(sourceAccount, {
+      fee: '100',
+      networkPassphrase: getNetworkPassphrase(),
+    })
+      .addOperation(acceptCall)
+      .setTimeout(30)
+      .build();
+
+    // Sign with Stellar Wallet Kit
+    const kit = (window as any).stellarWalletsKit;
+    if (!kit) {
+      throw new Error('No Stellar wallet available. Please connect your wallet.');
+    }
+
+    const { signedTxXdr } = await kit.signTransaction(tx.toXDR(), {
+      networkPassphrase: getNetworkPassphrase(),
+      address: userAddress,
+    });
+    
+    const txResponse = await server.sendTransaction(signedTxXdr);
+    
+    if (txResponse.status === 'ERROR') {
+      throw new Error(txResponse.errorResult?.toString() || 'Transaction failed');
+    }
+    
+    return txResponse.hash;
+  } catch (error) {
+    console.error('Error accepting ownership:', error);
+    throw error;
+  }
+}
+
 // Check if user has Stellar wallet available
 export function isWalletAvailable(): boolean {
   if (typeof window === 'undefined') return false;
@@ -1,9 +1,54 @@
 #![no_std]
+// Events are still emitted through `Events::publish`, which SDK 23 marks deprecated.
+#![allow(deprecated)]
 
 use soroban_sdk::{
-    contract, contractimpl, contracttype, Address, Env, String, Vec, BytesN, Symbol, IntoVal
+    assert_with_error, contract, contracterror, contractimpl, contracttype, panic_with_error,
+    Address, Env, String, Vec, BytesN
 };
 
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum FactoryError {
+    AlreadyInitialized = 1,
+    NotInitialized = 2,
+    NotOwner = 3,
+    MismatchedArrays = 4,
+    InvalidMarket = 5,
+    LivestreamAlreadyInMarket = 6,
+    FeeTooHigh = 7,
+    NoPendingOwner = 8,
+    NotPendingOwner = 9,
+    TooManyLivestreams = 10,
+    FactoryPaused = 11,
+    FactoryNotPaused = 12,
+    TooManyMarkets = 13,
+    NoPendingFork = 14,
+    BatchTooLarge = 15,
+}
+
+// Client and types for the prediction market contract this factory deploys
+#[allow(clippy::too_many_arguments)]
+mod market {
+    soroban_sdk::contractimport!(file = "../../target/wasm32v1-none/release/hello_world.wasm");
+}
+
+// Mirrors the prediction market's fee cap
+const MAX_FEE_BPS: u32 = 2000;
+
+// Most livestream ids accepted by a single multi-livestream query or update
+const MAX_QUERY_LIVESTREAMS: u32 = 20;
+
+// Most markets checked by a single cross-market query
+const MAX_QUERY_MARKETS: u32 = 20;
+
+// Most markets deployed by a single `create_markets` call
+const MAX_BATCH: u32 = 10;
+
+// Bettors `fork_market` migrates before leaving the rest to `continue_fork`
+const FORK_PAGE_SIZE: u32 = 50;
+
 #[contracttype]
 pub enum DataKey {
     Owner,
@@ -11,24 +56,47 @@ pub enum DataKey {
     ValidMarkets(Address), // market_address -> bool
     MarketToLivestreams(Address), // market_address -> Vec<u64>
     AllMarkets,
+    MarketQuestion(Address), // market_address -> question
+    MarketState(Address), // market_address -> last state reported by the market
+    DeployNonce,
+    Token,
+    DefaultFeeBps,
+    DefaultFeeCollector,
+    GlobalVolume, // sum of pools reported by closed markets
+    GlobalBettors, // sum of bettor counts reported by closed markets
+    PendingOwner, // nominated by transfer_ownership, set as owner on accept
+    FactoryPaused, // blocks market creation and livestream additions
+    Featured(Address), // market_address -> bool
+    FeaturedMarkets, // featured markets, in the order they were featured
+    MarketCreator(Address), // market_address -> account that created it
+    QuestionHashToMarket(BytesN<32>), // sha256(question) -> latest market asking it
+    PendingFork(Address), // source market -> (moved ids, new market, bettors checked) of an unfinished fork
 }
 
+// Mirrors the prediction market's `State` discriminants
+const MARKET_STATE_OPEN: u32 = 0;
+const MARKET_STATE_CLOSED: u32 = 1;
+
+// How long a market may stay open before the owner can force-close it
+const MARKET_OPEN_TIMEOUT: u64 = 30 * 24 * 60 * 60;
+
 #[contract]
 pub struct MarketFactory;
 
 #[contractimpl]
 impl MarketFactory {
     /// Initialize the factory
-    pub fn initialize(env: Env, owner: Address) {
-        owner.require_auth();
+    ///
+    /// Only the `deployer` has to sign, so the owner can be set to a
+    /// different address (e.g. a multisig) from a deployment script.
+    pub fn initialize(env: Env, deployer: Address, owner: Address, token: Address) {
+        deployer.require_auth();
         
-        // Check if already initialized - if Owner exists, contract is already initialized
-        if env.storage().instance().has(&DataKey::Owner) {
-            // Contract is already initialized, just return
-            return;
-        }
+        let initialized = env.storage().instance().has(&DataKey::Owner);
+        assert_with_error!(&env, !initialized, FactoryError::AlreadyInitialized);
         
         env.storage().instance().set(&DataKey::Owner, &owner);
+        env.storage().instance().set(&DataKey::Token, &token);
         env.storage().instance().set(&DataKey::AllMarkets, &Vec::<Address>::new(&env));
         
         env.events().publish(
@@ -45,69 +113,168 @@ impl MarketFactory {
         question: String,
         livestream_titles: Vec<String>,
         wasm_hash: BytesN<32>,
+        fee_bps: Option<u32>,
     ) -> Address {
         caller.require_auth();
         
         let owner: Address = env.storage().instance().get(&DataKey::Owner)
-            .expect("Contract not initialized");
-        assert!(caller == owner, "Not owner");
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
         
-        assert!(
-            livestream_ids.len() == livestream_titles.len(),
-            "Mismatched arrays"
+        Self::assert_not_paused(&env);
+        
+        if let Some(fee_bps) = fee_bps {
+            assert_with_error!(&env, fee_bps <= MAX_FEE_BPS, FactoryError::FeeTooHigh);
+        }
+        
+        Self::deploy_market(&env, &owner, &caller, livestream_ids, question, livestream_titles, wasm_hash, fee_bps)
+    }
+
+    /// Create up to `MAX_BATCH` markets in one transaction
+    pub fn create_markets(
+        env: Env,
+        caller: Address,
+        questions: Vec<String>,
+        livestream_ids_list: Vec<Vec<u64>>,
+        titles_list: Vec<Vec<String>>,
+        wasm_hash: BytesN<32>,
+    ) -> Vec<Address> {
+        caller.require_auth();
+        
+        let owner: Address = env.storage().instance().get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
+        
+        Self::assert_not_paused(&env);
+        
+        assert_with_error!(
+            &env,
+            questions.len() == livestream_ids_list.len() && questions.len() == titles_list.len(),
+            FactoryError::MismatchedArrays
         );
+        assert_with_error!(&env, questions.len() <= MAX_BATCH, FactoryError::BatchTooLarge);
         
-        // Deploy new prediction market contract
-        let question_bytes = question.to_bytes();
-        let salt_hash = env.crypto().sha256(&question_bytes);
-        let salt_array = salt_hash.to_array();
-        let salt = BytesN::from_array(&env, &salt_array);
+        let mut markets: Vec<Address> = Vec::new(&env);
+        for i in 0..questions.len() {
+            let market_address = Self::deploy_market(
+                &env,
+                &owner,
+                &caller,
+                livestream_ids_list.get(i).unwrap(),
+                questions.get(i).unwrap(),
+                titles_list.get(i).unwrap(),
+                wasm_hash.clone(),
+                None,
+            );
+            markets.push_back(market_address);
+        }
         
-        // Deploy the market contract using the provided WASM hash
-        // The WASM must be installed on the network before calling this
-        let market_address = env.deployer()
-            .with_current_contract(salt)
-            .deploy_v2(wasm_hash, ());
+        markets
+    }
+
+    /// Split livestreams out of a market into a new one, carrying their bets along
+    ///
+    /// The moved pool is transferred to the new market, which is deployed from
+    /// `wasm_hash` like any other market. Bets are migrated for the first
+    /// `FORK_PAGE_SIZE` bettors; larger markets finish with `continue_fork`.
+    pub fn fork_market(
+        env: Env,
+        caller: Address,
+        source_market: Address,
+        ids_to_move: Vec<u64>,
+        new_question: String,
+        wasm_hash: BytesN<32>,
+    ) -> Address {
+        caller.require_auth();
         
-        // TODO: Cross-contract initialization disabled for now
-        // The market needs to be initialized separately after deployment
-        // This is because cross-contract calls require additional authorization handling
-        // 
-        // Initialize parameters would be:
-        // - livestream_ids, question, livestream_titles, oracle (owner), factory (this contract)
-        //
-        // For now, return the market address and let the frontend handle initialization
-        let _factory_address = env.current_contract_address();
-        let _oracle_address = owner.clone();
+        let owner: Address = env.storage().instance().get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
         
-        // Store market info
-        env.storage().persistent().set(&DataKey::ValidMarkets(market_address.clone()), &true);
-        env.storage().persistent().set(&DataKey::MarketToLivestreams(market_address.clone()), &livestream_ids);
+        Self::assert_not_paused(&env);
         
-        let mut all_markets: Vec<Address> = env.storage().instance().get(&DataKey::AllMarkets)
-            .unwrap_or(Vec::<Address>::new(&env));
-        all_markets.push_back(market_address.clone());
-        env.storage().instance().set(&DataKey::AllMarkets, &all_markets);
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(source_market.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        // Each page of the fork checks every moved livestream per bettor
+        assert_with_error!(
+            &env,
+            ids_to_move.len() <= MAX_QUERY_LIVESTREAMS,
+            FactoryError::TooManyLivestreams
+        );
         
-        // Add market to each livestream's market list
-        for i in 0..livestream_ids.len() {
-            let livestream_id = livestream_ids.get(i).unwrap();
-            
-            let mut markets = env.storage()
+        let source_client = market::Client::new(&env, &source_market);
+        let mut titles: Vec<String> = Vec::new(&env);
+        for id in ids_to_move.iter() {
+            titles.push_back(source_client.get_livestream(&id).title);
+        }
+        
+        let creator: Address = env.storage()
+            .persistent()
+            .get(&DataKey::MarketCreator(source_market.clone()))
+            .unwrap_or(owner.clone());
+        let new_market = Self::deploy_market(&env, &owner, &creator, ids_to_move.clone(), new_question, titles, wasm_hash, None);
+        
+        Self::fork_page(&env, &source_market, &ids_to_move, &new_market, 0, FORK_PAGE_SIZE);
+        
+        // The moved livestreams now belong to the new market only
+        let source_ids: Vec<u64> = env.storage()
+            .persistent()
+            .get(&DataKey::MarketToLivestreams(source_market.clone()))
+            .unwrap();
+        let mut remaining: Vec<u64> = Vec::new(&env);
+        for id in source_ids.iter() {
+            if !ids_to_move.contains(id) {
+                remaining.push_back(id);
+            }
+        }
+        env.storage().persistent().set(&DataKey::MarketToLivestreams(source_market.clone()), &remaining);
+        
+        for id in ids_to_move.iter() {
+            let mut livestream_markets: Vec<Address> = env.storage()
                 .persistent()
-                .get(&DataKey::LivestreamMarkets(livestream_id))
-                .unwrap_or(Vec::<Address>::new(&env));
-            
-            markets.push_back(market_address.clone());
-            env.storage().persistent().set(&DataKey::LivestreamMarkets(livestream_id), &markets);
+                .get(&DataKey::LivestreamMarkets(id))
+                .unwrap_or(Vec::new(&env));
+            if let Some(index) = livestream_markets.first_index_of(&source_market) {
+                livestream_markets.remove(index);
+            }
+            env.storage().persistent().set(&DataKey::LivestreamMarkets(id), &livestream_markets);
         }
         
         env.events().publish(
-            (String::from_str(&env, "market_created"),),
-            (market_address.clone(), question, livestream_ids.clone())
+            (String::from_str(&env, "market_forked"),),
+            (source_market, new_market.clone(), ids_to_move)
         );
         
-        market_address
+        new_market
+    }
+
+    /// Migrate the next `limit` bettors of an unfinished fork
+    ///
+    /// Returns how many bettors are still left to check.
+    pub fn continue_fork(env: Env, caller: Address, source_market: Address, limit: u32) -> u32 {
+        caller.require_auth();
+        
+        let owner: Address = env.storage().instance().get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
+        
+        let (ids_to_move, new_market, checked): (Vec<u64>, Address, u32) = env.storage()
+            .persistent()
+            .get(&DataKey::PendingFork(source_market.clone()))
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NoPendingFork));
+        
+        Self::fork_page(&env, &source_market, &ids_to_move, &new_market, checked, limit)
+    }
+
+    /// Get the new market of an unfinished fork out of `source_market`, if any
+    pub fn get_pending_fork(env: Env, source_market: Address) -> Option<Address> {
+        env.storage()
+            .persistent()
+            .get::<_, (Vec<u64>, Address, u32)>(&DataKey::PendingFork(source_market))
+            .map(|(_, new_market, _)| new_market)
     }
 
     /// Add a livestream to an existing market
@@ -120,14 +287,16 @@ impl MarketFactory {
     ) {
         caller.require_auth();
         
-        let owner: Address = env.storage().instance().get(&DataKey::Owner).unwrap();
-        assert!(caller == owner, "Not owner");
+        let owner: Address = env.storage().instance().get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
+        Self::assert_not_paused(&env);
         
         let is_valid: bool = env.storage()
             .persistent()
             .get(&DataKey::ValidMarkets(market_address.clone()))
             .unwrap_or(false);
-        assert!(is_valid, "Invalid market");
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
         
         // Check if livestream is already in market
         let current_livestreams: Vec<u64> = env.storage()
@@ -137,7 +306,7 @@ impl MarketFactory {
         
         for i in 0..current_livestreams.len() {
             let id = current_livestreams.get(i).unwrap();
-            assert!(id != livestream_id, "Livestream already in market");
+            assert_with_error!(&env, id != livestream_id, FactoryError::LivestreamAlreadyInMarket);
         }
         
         // Update mappings
@@ -170,14 +339,15 @@ impl MarketFactory {
     ) {
         caller.require_auth();
         
-        let owner: Address = env.storage().instance().get(&DataKey::Owner).unwrap();
-        assert!(caller == owner, "Not owner");
+        let owner: Address = env.storage().instance().get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
         
         let is_valid: bool = env.storage()
             .persistent()
             .get(&DataKey::ValidMarkets(market_address.clone()))
             .unwrap_or(false);
-        assert!(is_valid, "Invalid market");
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
         
         // Update marketToLivestreams mapping
         let market_livestreams: Vec<u64> = env.storage()
@@ -218,6 +388,74 @@ impl MarketFactory {
         );
     }
 
+    /// Replace a market's whole livestream set, keeping the reverse mappings in step
+    pub fn set_market_livestreams(env: Env, caller: Address, market_address: Address, livestream_ids: Vec<u64>) {
+        caller.require_auth();
+        
+        let owner: Address = env.storage().instance().get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
+        
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(market_address.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        assert_with_error!(
+            &env,
+            livestream_ids.len() <= MAX_QUERY_LIVESTREAMS,
+            FactoryError::TooManyLivestreams
+        );
+        for i in 0..livestream_ids.len() {
+            let id = livestream_ids.get(i).unwrap();
+            assert_with_error!(
+                &env,
+                livestream_ids.first_index_of(id) == Some(i),
+                FactoryError::LivestreamAlreadyInMarket
+            );
+        }
+        
+        let old_ids: Vec<u64> = env.storage()
+            .persistent()
+            .get(&DataKey::MarketToLivestreams(market_address.clone()))
+            .unwrap();
+        
+        // Drop the market from livestreams leaving it
+        for id in old_ids.iter() {
+            if livestream_ids.contains(id) {
+                continue;
+            }
+            let mut livestream_markets: Vec<Address> = env.storage()
+                .persistent()
+                .get(&DataKey::LivestreamMarkets(id))
+                .unwrap_or(Vec::new(&env));
+            if let Some(index) = livestream_markets.first_index_of(&market_address) {
+                livestream_markets.remove(index);
+            }
+            env.storage().persistent().set(&DataKey::LivestreamMarkets(id), &livestream_markets);
+        }
+        
+        // Add it to livestreams joining it
+        for id in livestream_ids.iter() {
+            if old_ids.contains(id) {
+                continue;
+            }
+            let mut livestream_markets: Vec<Address> = env.storage()
+                .persistent()
+                .get(&DataKey::LivestreamMarkets(id))
+                .unwrap_or(Vec::new(&env));
+            livestream_markets.push_back(market_address.clone());
+            env.storage().persistent().set(&DataKey::LivestreamMarkets(id), &livestream_markets);
+        }
+        
+        env.storage().persistent().set(&DataKey::MarketToLivestreams(market_address.clone()), &livestream_ids);
+        
+        env.events().publish(
+            (String::from_str(&env, "market_livestreams_set"),),
+            (market_address, livestream_ids)
+        );
+    }
+
     /// Get all markets for a specific livestream
     pub fn get_markets_for_livestream(env: Env, livestream_id: u64) -> Vec<Address> {
         env.storage()
@@ -226,6 +464,255 @@ impl MarketFactory {
             .unwrap_or(Vec::<Address>::new(&env))
     }
 
+    /// Get every market that includes any of the given livestreams, without duplicates
+    pub fn get_markets_for_livestreams(env: Env, livestream_ids: Vec<u64>) -> Vec<Address> {
+        assert_with_error!(
+            &env,
+            livestream_ids.len() <= MAX_QUERY_LIVESTREAMS,
+            FactoryError::TooManyLivestreams
+        );
+        
+        let mut result: Vec<Address> = Vec::new(&env);
+        for livestream_id in livestream_ids.iter() {
+            let markets: Vec<Address> = env.storage()
+                .persistent()
+                .get(&DataKey::LivestreamMarkets(livestream_id))
+                .unwrap_or(Vec::<Address>::new(&env));
+            for market in markets.iter() {
+                if !result.contains(&market) {
+                    result.push_back(market);
+                }
+            }
+        }
+        
+        result
+    }
+
+    /// Get the markets among `markets` where `user` could claim a payout now
+    ///
+    /// Covers weighted resolutions and leaves out markets whose claim window
+    /// has closed. Addresses that aren't markets from this factory are skipped.
+    pub fn get_claimable_markets(env: Env, user: Address, markets: Vec<Address>) -> Vec<Address> {
+        assert_with_error!(&env, markets.len() <= MAX_QUERY_MARKETS, FactoryError::TooManyMarkets);
+        
+        let mut result: Vec<Address> = Vec::new(&env);
+        for market_address in markets.iter() {
+            let is_valid: bool = env.storage()
+                .persistent()
+                .get(&DataKey::ValidMarkets(market_address.clone()))
+                .unwrap_or(false);
+            if !is_valid {
+                continue;
+            }
+            
+            if market::Client::new(&env, &market_address).is_claimable(&user) {
+                result.push_back(market_address);
+            }
+        }
+        
+        result
+    }
+
+    /// Get a market's dashboard card: (question, state, livestream_count, pool, bettors)
+    pub fn get_market_details(env: Env, market_address: Address) -> (String, u32, u32, i128, u64) {
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(market_address.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        let (livestream_ids, question, state, _, pool, bettors, _) =
+            market::Client::new(&env, &market_address).get_market_info();
+        
+        (question, state as u32, livestream_ids.len(), pool, bettors)
+    }
+
+    /// Get (address, state, pool) for each of `markets` from this factory
+    ///
+    /// Addresses that aren't markets from this factory are skipped.
+    pub fn get_markets_info(env: Env, markets: Vec<Address>) -> Vec<(Address, u32, i128)> {
+        assert_with_error!(&env, markets.len() <= MAX_QUERY_MARKETS, FactoryError::TooManyMarkets);
+        
+        let mut result: Vec<(Address, u32, i128)> = Vec::new(&env);
+        for market_address in markets.iter() {
+            let is_valid: bool = env.storage()
+                .persistent()
+                .get(&DataKey::ValidMarkets(market_address.clone()))
+                .unwrap_or(false);
+            if !is_valid {
+                continue;
+            }
+            
+            let (_, _, state, _, pool, _, _) = market::Client::new(&env, &market_address).get_market_info();
+            result.push_back((market_address, state as u32, pool));
+        }
+        
+        result
+    }
+
+    /// Get the bettor with the largest stake on a market's livestream and that stake
+    ///
+    /// Scans every bettor of the market, so it's meant for simulation only.
+    /// Returns the market's own address with 0 when nobody backed the livestream.
+    pub fn get_top_bettor(env: Env, market_address: Address, livestream_id: u64) -> (Address, i128) {
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(market_address.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        let market_client = market::Client::new(&env, &market_address);
+        let mut top = (market_address.clone(), 0i128);
+        for (bettor, amount) in Self::livestream_backers(&market_client, livestream_id).iter() {
+            if amount > top.1 {
+                top = (bettor, amount);
+            }
+        }
+        
+        top
+    }
+
+    /// Get a user's rank by stake on a market's livestream and how many users backed it
+    ///
+    /// Rank 1 is the largest stake; ties go to whoever placed their first bet
+    /// earliest. The rank is 0 if the user has no stake there. Scans every
+    /// bettor of the market, so it's meant for simulation only.
+    pub fn get_bettor_rank(env: Env, market_address: Address, user: Address, livestream_id: u64) -> (u32, u32) {
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(market_address.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        let market_client = market::Client::new(&env, &market_address);
+        let backers = Self::livestream_backers(&market_client, livestream_id);
+        let user_amount = market_client.get_user_bet(&user, &livestream_id);
+        let user_first_bet = market_client.get_first_bet(&user);
+        
+        let mut ahead: u32 = 0;
+        for (bettor, amount) in backers.iter() {
+            if bettor != user
+                && (amount > user_amount
+                    || (amount == user_amount && market_client.get_first_bet(&bettor) < user_first_bet))
+            {
+                ahead += 1;
+            }
+        }
+        
+        let rank = if user_amount > 0 { ahead + 1 } else { 0 };
+        (rank, backers.len())
+    }
+
+    /// Expected payout of a user's bets in a market at current odds, net of fees
+    ///
+    /// Each livestream is treated as winning with probability equal to its
+    /// share of all bets, `total_bets(i) / sum(total_bets)`, and would pay
+    /// `user_bet(i) * total_pool / total_bets(i)`. The value is the sum of
+    /// those products, which reduces to the user's stake scaled by
+    /// `total_pool / sum(total_bets)` (above 1 only with a bonus pool).
+    pub fn get_user_position_value(env: Env, market_address: Address, user: Address) -> i128 {
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(market_address.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        let market_client = market::Client::new(&env, &market_address);
+        let (ids, _, _, _, total_pool, _, _) = market_client.get_market_info();
+        
+        let mut livestream_totals: Vec<i128> = Vec::new(&env);
+        let mut all_bets: i128 = 0;
+        for id in ids.iter() {
+            let (amount, _, _) = market_client.get_livestream_bets(&id);
+            livestream_totals.push_back(amount);
+            all_bets += amount;
+        }
+        if all_bets == 0 {
+            return 0;
+        }
+        
+        let mut value: i128 = 0;
+        for (i, id) in ids.iter().enumerate() {
+            let user_bet = market_client.get_user_bet(&user, &id);
+            if user_bet == 0 {
+                continue;
+            }
+            let livestream_total = livestream_totals.get(i as u32).unwrap();
+            let payout_if_wins = (user_bet * total_pool) / livestream_total;
+            value += (payout_if_wins * livestream_total) / all_bets;
+        }
+        
+        let (fee_bps, _, creator_fee_bps, _) = market_client.get_fee_config();
+        value - (value * (fee_bps + creator_fee_bps) as i128) / 10_000
+    }
+
+    /// Get a market's livestream betting data with the pool share in basis points (0-10000)
+    pub fn get_livestream_bets_bps(env: Env, market_address: Address, livestream_id: u64) -> (i128, u64, bool) {
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(market_address.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        let market_client = market::Client::new(&env, &market_address);
+        let (amount, _, active) = market_client.get_livestream_bets(&livestream_id);
+        let (_, _, _, _, total_pool, _, _) = market_client.get_market_info();
+        let share_bps = if total_pool > 0 {
+            ((amount * 10_000) / total_pool) as u64
+        } else {
+            0
+        };
+        
+        (amount, share_bps, active)
+    }
+
+    /// Get a market's total bets per group as (group_id, total), in order of first appearance
+    ///
+    /// Ungrouped livestreams are left out.
+    pub fn get_group_totals(env: Env, market_address: Address) -> Vec<(u64, i128)> {
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(market_address.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        let market_client = market::Client::new(&env, &market_address);
+        let (ids, _, _, _, _, _, _) = market_client.get_market_info();
+        
+        let mut totals: Vec<(u64, i128)> = Vec::new(&env);
+        for id in ids.iter() {
+            let group = market_client.get_group(&id);
+            if group == 0 {
+                continue;
+            }
+            let (amount, _, _) = market_client.get_livestream_bets(&id);
+            
+            match totals.iter().position(|(g, _)| g == group) {
+                Some(i) => {
+                    let (_, total) = totals.get(i as u32).unwrap();
+                    totals.set(i as u32, (group, total + amount));
+                }
+                None => totals.push_back((group, amount)),
+            }
+        }
+        
+        totals
+    }
+
+    /// Check a market's livestream without panicking: (exists, active)
+    pub fn livestream_exists(env: Env, market_address: Address, livestream_id: u64) -> (bool, bool) {
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(market_address.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        match market::Client::new(&env, &market_address).try_get_livestream(&livestream_id) {
+            Ok(Ok(livestream)) => (true, livestream.active),
+            _ => (false, false),
+        }
+    }
+
     /// Get market count for a livestream
     pub fn get_market_count_for_livestream(env: Env, livestream_id: u64) -> u32 {
         let markets: Vec<Address> = env.storage()
@@ -252,11 +739,11 @@ impl MarketFactory {
             .unwrap_or(Vec::<Address>::new(&env));
         
         // Return empty vector if no markets exist or offset is at/beyond end
-        if all_markets.len() == 0 || offset >= all_markets.len() {
+        if all_markets.is_empty() || offset >= all_markets.len() {
             return Vec::<Address>::new(&env);
         }
         
-        let end = if offset + limit > all_markets.len() {
+        let end = if offset.saturating_add(limit) > all_markets.len() {
             all_markets.len()
         } else {
             offset + limit
@@ -270,13 +757,95 @@ impl MarketFactory {
         result
     }
 
+    /// Feature or unfeature a market for promotion
+    pub fn set_featured(env: Env, caller: Address, market_address: Address, featured: bool) {
+        caller.require_auth();
+        
+        let owner: Address = env.storage().instance().get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
+        
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(market_address.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        let was_featured: bool = env.storage()
+            .persistent()
+            .get(&DataKey::Featured(market_address.clone()))
+            .unwrap_or(false);
+        if was_featured == featured {
+            return;
+        }
+        
+        let mut featured_markets: Vec<Address> = env.storage()
+            .instance()
+            .get(&DataKey::FeaturedMarkets)
+            .unwrap_or(Vec::<Address>::new(&env));
+        if featured {
+            featured_markets.push_back(market_address.clone());
+        } else if let Some(index) = featured_markets.first_index_of(&market_address) {
+            featured_markets.remove(index);
+        }
+        env.storage().instance().set(&DataKey::FeaturedMarkets, &featured_markets);
+        env.storage().persistent().set(&DataKey::Featured(market_address.clone()), &featured);
+        
+        env.events().publish(
+            (String::from_str(&env, "market_featured"),),
+            (market_address, featured)
+        );
+    }
+
+    /// Get featured markets (paginated)
+    pub fn get_featured_markets(env: Env, offset: u32, limit: u32) -> Vec<Address> {
+        let featured_markets: Vec<Address> = env.storage()
+            .instance()
+            .get(&DataKey::FeaturedMarkets)
+            .unwrap_or(Vec::<Address>::new(&env));
+        
+        let mut result: Vec<Address> = Vec::new(&env);
+        if offset >= featured_markets.len() {
+            return result;
+        }
+        
+        let end = offset.saturating_add(limit).min(featured_markets.len());
+        for i in offset..end {
+            result.push_back(featured_markets.get(i).unwrap());
+        }
+        
+        result
+    }
+
+    /// Get all markets newest-first (paginated)
+    pub fn get_recent_markets(env: Env, offset: u32, limit: u32) -> Vec<Address> {
+        let all_markets: Vec<Address> = env.storage()
+            .instance()
+            .get(&DataKey::AllMarkets)
+            .unwrap_or(Vec::<Address>::new(&env));
+        
+        let mut result: Vec<Address> = Vec::new(&env);
+        if offset >= all_markets.len() {
+            return result;
+        }
+        
+        // Walk backwards from the newest market, skipping `offset` entries
+        let start = all_markets.len() - offset;
+        let end = start.saturating_sub(limit);
+        for i in (end..start).rev() {
+            result.push_back(all_markets.get(i).unwrap());
+        }
+        
+        result
+    }
+
     /// Get livestream IDs for a market
     pub fn get_livestreams_for_market(env: Env, market_address: Address) -> Vec<u64> {
         let is_valid: bool = env.storage()
             .persistent()
             .get(&DataKey::ValidMarkets(market_address.clone()))
             .unwrap_or(false);
-        assert!(is_valid, "Invalid market");
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
         
         env.storage()
             .persistent()
@@ -284,6 +853,30 @@ impl MarketFactory {
             .unwrap()
     }
 
+    /// Get a market's question, livestream count and last reported state
+    pub fn get_market_summary(env: Env, market_address: Address) -> (String, u32, u32) {
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(market_address.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        let question: String = env.storage()
+            .persistent()
+            .get(&DataKey::MarketQuestion(market_address.clone()))
+            .unwrap();
+        let livestream_ids: Vec<u64> = env.storage()
+            .persistent()
+            .get(&DataKey::MarketToLivestreams(market_address.clone()))
+            .unwrap();
+        let state: u32 = env.storage()
+            .persistent()
+            .get(&DataKey::MarketState(market_address))
+            .unwrap_or(MARKET_STATE_OPEN);
+        
+        (question, livestream_ids.len(), state)
+    }
+
     /// Check if a livestream is in a specific market
     pub fn is_livestream_in_market(
         env: Env,
@@ -315,12 +908,17 @@ impl MarketFactory {
 
     /// Called by markets when they are closed
     pub fn notify_market_closed(env: Env, caller: Address, livestream_ids: Vec<u64>) {
+        // Only the market contract itself can authorize this
+        caller.require_auth();
+        
         // Verify caller is a valid market
         let is_valid: bool = env.storage()
             .persistent()
             .get(&DataKey::ValidMarkets(caller.clone()))
             .unwrap_or(false);
-        assert!(is_valid, "Not a valid market");
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        env.storage().persistent().set(&DataKey::MarketState(caller.clone()), &MARKET_STATE_CLOSED);
         
         env.events().publish(
             (String::from_str(&env, "market_closed"),),
@@ -328,22 +926,182 @@ impl MarketFactory {
         );
     }
 
-    /// Transfer ownership
+    /// Close a market whose oracle never did, once its open timeout has passed
+    pub fn force_close_market(env: Env, caller: Address, market_address: Address) {
+        caller.require_auth();
+        
+        let owner: Address = env.storage().instance().get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
+        
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(market_address.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        // The market can't call back into us here, so record the close and stats directly
+        let market_client = market::Client::new(&env, &market_address);
+        market_client.force_close(&env.current_contract_address());
+        env.storage().persistent().set(&DataKey::MarketState(market_address.clone()), &MARKET_STATE_CLOSED);
+        
+        let (livestream_ids, _, _, _, pool, bettors, _) = market_client.get_market_info();
+        let volume: i128 = env.storage().instance().get(&DataKey::GlobalVolume).unwrap_or(0);
+        let total_bettors: u64 = env.storage().instance().get(&DataKey::GlobalBettors).unwrap_or(0);
+        env.storage().instance().set(&DataKey::GlobalVolume, &(volume + pool));
+        env.storage().instance().set(&DataKey::GlobalBettors, &(total_bettors + bettors));
+        
+        env.events().publish(
+            (String::from_str(&env, "market_closed"),),
+            (market_address, livestream_ids)
+        );
+    }
+
+    /// Add a closed market's final pool and bettor count to the global totals
+    pub fn report_stats(env: Env, caller_market: Address, pool: i128, bettors: u64) {
+        caller_market.require_auth();
+        
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(caller_market.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        let volume: i128 = env.storage().instance().get(&DataKey::GlobalVolume).unwrap_or(0);
+        let total_bettors: u64 = env.storage().instance().get(&DataKey::GlobalBettors).unwrap_or(0);
+        env.storage().instance().set(&DataKey::GlobalVolume, &(volume + pool));
+        env.storage().instance().set(&DataKey::GlobalBettors, &(total_bettors + bettors));
+    }
+
+    /// Mark a reopened market open again and withdraw the stats it reported at close
+    pub fn notify_market_reopened(env: Env, caller_market: Address, pool: i128, bettors: u64) {
+        caller_market.require_auth();
+        
+        let is_valid: bool = env.storage()
+            .persistent()
+            .get(&DataKey::ValidMarkets(caller_market.clone()))
+            .unwrap_or(false);
+        assert_with_error!(&env, is_valid, FactoryError::InvalidMarket);
+        
+        env.storage().persistent().set(&DataKey::MarketState(caller_market.clone()), &MARKET_STATE_OPEN);
+        
+        let volume: i128 = env.storage().instance().get(&DataKey::GlobalVolume).unwrap_or(0);
+        let total_bettors: u64 = env.storage().instance().get(&DataKey::GlobalBettors).unwrap_or(0);
+        env.storage().instance().set(&DataKey::GlobalVolume, &(volume - pool));
+        env.storage().instance().set(&DataKey::GlobalBettors, &total_bettors.saturating_sub(bettors));
+        
+        env.events().publish(
+            (String::from_str(&env, "market_reopened"),),
+            caller_market
+        );
+    }
+
+    /// Get (total volume, total bettors) across all markets that have reported
+    pub fn get_global_stats(env: Env) -> (i128, u64) {
+        let volume: i128 = env.storage().instance().get(&DataKey::GlobalVolume).unwrap_or(0);
+        let bettors: u64 = env.storage().instance().get(&DataKey::GlobalBettors).unwrap_or(0);
+        (volume, bettors)
+    }
+
+    /// Set the platform fee applied to markets created from now on
+    pub fn set_default_fee(env: Env, caller: Address, fee_bps: u32, fee_collector: Address) {
+        caller.require_auth();
+        
+        let owner: Address = env.storage().instance().get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
+        assert_with_error!(&env, fee_bps <= MAX_FEE_BPS, FactoryError::FeeTooHigh);
+        
+        env.storage().instance().set(&DataKey::DefaultFeeBps, &fee_bps);
+        env.storage().instance().set(&DataKey::DefaultFeeCollector, &fee_collector);
+        
+        env.events().publish(
+            (String::from_str(&env, "default_fee_set"),),
+            (fee_bps, fee_collector)
+        );
+    }
+
+    /// Get the default fee (bps, collector) passed to new markets
+    pub fn get_default_fee(env: Env) -> (u32, Address) {
+        Self::default_fee(&env)
+    }
+
+    /// Stop market creation and livestream additions during an incident
+    pub fn pause_factory(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        let owner: Address = env.storage().instance().get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
+        Self::assert_not_paused(&env);
+        
+        env.storage().instance().set(&DataKey::FactoryPaused, &true);
+        
+        env.events().publish(
+            (String::from_str(&env, "factory_paused"),),
+            env.ledger().timestamp()
+        );
+    }
+
+    /// Allow market creation again
+    pub fn unpause_factory(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        let owner: Address = env.storage().instance().get(&DataKey::Owner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
+        
+        let paused: bool = env.storage().instance().get(&DataKey::FactoryPaused).unwrap_or(false);
+        assert_with_error!(&env, paused, FactoryError::FactoryNotPaused);
+        
+        env.storage().instance().set(&DataKey::FactoryPaused, &false);
+        
+        env.events().publish(
+            (String::from_str(&env, "factory_unpaused"),),
+            env.ledger().timestamp()
+        );
+    }
+
+    /// Start an ownership transfer; `new_owner` must call `accept_ownership`
     pub fn transfer_ownership(env: Env, caller: Address, new_owner: Address) {
         caller.require_auth();
         
         let owner: Address = env.storage().instance().get(&DataKey::Owner)
-            .expect("Contract not initialized");
-        assert!(caller == owner, "Not owner");
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized));
+        assert_with_error!(&env, caller == owner, FactoryError::NotOwner);
         
-        env.storage().instance().set(&DataKey::Owner, &new_owner);
+        env.storage().instance().set(&DataKey::PendingOwner, &new_owner);
         
         env.events().publish(
-            (String::from_str(&env, "ownership_transferred"),),
+            (String::from_str(&env, "ownership_transfer_initiated"),),
             (caller, new_owner)
         );
     }
 
+    /// Finish an ownership transfer as the pending owner
+    pub fn accept_ownership(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        let pending: Address = env.storage().instance().get(&DataKey::PendingOwner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NoPendingOwner));
+        assert_with_error!(&env, caller == pending, FactoryError::NotPendingOwner);
+        
+        let previous: Address = env.storage().instance().get(&DataKey::Owner).unwrap();
+        env.storage().instance().set(&DataKey::Owner, &caller);
+        env.storage().instance().remove(&DataKey::PendingOwner);
+        
+        env.events().publish(
+            (String::from_str(&env, "ownership_transferred"),),
+            (previous, caller)
+        );
+    }
+
+    /// Get the address nominated to become owner
+    pub fn get_pending_owner(env: Env) -> Address {
+        env.storage().instance().get(&DataKey::PendingOwner)
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NoPendingOwner))
+    }
+
     /// Check if a market exists
     pub fn is_valid_market(env: Env, market_address: Address) -> bool {
         env.storage()
@@ -352,10 +1110,239 @@ impl MarketFactory {
             .unwrap_or(false)
     }
 
+    /// Check that a market is registered here and names this factory as its own
+    ///
+    /// Catches impostor markets that were initialized with a different
+    /// factory address than the one that registered them.
+    pub fn verify_market_factory(env: Env, market_address: Address) -> bool {
+        if !Self::is_valid_market(env.clone(), market_address.clone()) {
+            return false;
+        }
+        
+        match market::Client::new(&env, &market_address).try_get_factory() {
+            Ok(Ok(factory)) => factory == env.current_contract_address(),
+            _ => false,
+        }
+    }
+
+    /// Get the account that created a market
+    pub fn get_market_creator(env: Env, market_address: Address) -> Address {
+        env.storage()
+            .persistent()
+            .get(&DataKey::MarketCreator(market_address))
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::InvalidMarket))
+    }
+
+    /// Address the next market created for `question` will be deployed at
+    pub fn predict_market_address(env: Env, question: String) -> Address {
+        let nonce: u64 = env.storage().instance().get(&DataKey::DeployNonce).unwrap_or(0);
+        let salt = Self::market_salt(&env, &question, nonce);
+        env.deployer().with_current_contract(salt).deployed_address()
+    }
+
+    /// Find the most recent market asking exactly `question`
+    pub fn find_market_by_question(env: Env, question: String) -> Option<Address> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::QuestionHashToMarket(Self::question_hash(&env, &question)))
+    }
+
+    /// Keep only the candidates that are markets from this factory
+    pub fn filter_valid_markets(env: Env, candidates: Vec<Address>) -> Vec<Address> {
+        assert_with_error!(&env, candidates.len() <= MAX_QUERY_MARKETS, FactoryError::TooManyMarkets);
+        
+        let mut result: Vec<Address> = Vec::new(&env);
+        for candidate in candidates.iter() {
+            let is_valid: bool = env.storage()
+                .persistent()
+                .get(&DataKey::ValidMarkets(candidate.clone()))
+                .unwrap_or(false);
+            if is_valid {
+                result.push_back(candidate);
+            }
+        }
+        
+        result
+    }
+
     /// Get current owner
     pub fn get_owner(env: Env) -> Address {
         env.storage().instance().get(&DataKey::Owner)
-            .expect("Contract not initialized")
+            .unwrap_or_else(|| panic_with_error!(&env, FactoryError::NotInitialized))
+    }
+
+    /// Check whether `initialize` has been called
+    pub fn is_initialized(env: Env) -> bool {
+        env.storage().instance().has(&DataKey::Owner)
+    }
+}
+
+impl MarketFactory {
+    fn assert_not_paused(env: &Env) {
+        let paused: bool = env.storage().instance().get(&DataKey::FactoryPaused).unwrap_or(false);
+        assert_with_error!(env, !paused, FactoryError::FactoryPaused);
+    }
+
+    /// Move one page of a fork's bets and record where the next page starts
+    fn fork_page(
+        env: &Env,
+        source_market: &Address,
+        ids_to_move: &Vec<u64>,
+        new_market: &Address,
+        offset: u32,
+        limit: u32,
+    ) -> u32 {
+        let factory = env.current_contract_address();
+        let (bets, remaining) = market::Client::new(env, source_market)
+            .fork_out(&factory, ids_to_move, new_market, &offset, &limit);
+        market::Client::new(env, new_market).fork_in(&factory, &bets);
+        
+        let key = DataKey::PendingFork(source_market.clone());
+        if remaining == 0 {
+            env.storage().persistent().remove(&key);
+        } else {
+            // Only a full page leaves bettors behind
+            env.storage().persistent().set(&key, &(ids_to_move.clone(), new_market.clone(), offset + limit));
+        }
+        
+        remaining
+    }
+
+    /// Every (bettor, stake) on one of a market's livestreams
+    fn livestream_backers(market_client: &market::Client, livestream_id: u64) -> Vec<(Address, i128)> {
+        let (_, _, _, _, _, total_bettors, _) = market_client.get_market_info();
+        market_client.get_livestream_bettors(&livestream_id, &0, &(total_bettors as u32))
+    }
+
+    /// Deploy salt; mixing in the deploy nonce gives identical questions distinct addresses
+    fn market_salt(env: &Env, question: &String, nonce: u64) -> BytesN<32> {
+        let mut salt_bytes = question.to_bytes();
+        salt_bytes.extend_from_array(&nonce.to_be_bytes());
+        let salt_hash = env.crypto().sha256(&salt_bytes);
+        let salt_array = salt_hash.to_array();
+        BytesN::from_array(env, &salt_array)
+    }
+
+    fn question_hash(env: &Env, question: &String) -> BytesN<32> {
+        env.crypto().sha256(&question.to_bytes()).into()
+    }
+
+    /// Default fee, collected by the owner until one is configured
+    fn default_fee(env: &Env) -> (u32, Address) {
+        let fee_bps: u32 = env.storage().instance().get(&DataKey::DefaultFeeBps).unwrap_or(0);
+        let fee_collector: Address = env.storage().instance().get(&DataKey::DefaultFeeCollector)
+            .unwrap_or_else(|| {
+                env.storage().instance().get(&DataKey::Owner)
+                    .unwrap_or_else(|| panic_with_error!(env, FactoryError::NotInitialized))
+            });
+        (fee_bps, fee_collector)
+    }
+
+    /// Deploy a market from `wasm_hash` and register it in the factory
+    #[allow(clippy::too_many_arguments)]
+    fn deploy_market(
+        env: &Env,
+        owner: &Address,
+        creator: &Address,
+        livestream_ids: Vec<u64>,
+        question: String,
+        livestream_titles: Vec<String>,
+        wasm_hash: BytesN<32>,
+        fee_override: Option<u32>,
+    ) -> Address {
+        assert_with_error!(
+            env,
+            livestream_ids.len() == livestream_titles.len(),
+            FactoryError::MismatchedArrays
+        );
+        
+        // Deploy new prediction market contract
+        let nonce: u64 = env.storage().instance().get(&DataKey::DeployNonce).unwrap_or(0);
+        env.storage().instance().set(&DataKey::DeployNonce, &(nonce + 1));
+        let salt = Self::market_salt(env, &question, nonce);
+        
+        // Deploy the market contract using the provided WASM hash
+        // The WASM must be installed on the network before calling this
+        let market_address = env.deployer()
+            .with_current_contract(salt)
+            .deploy_v2(wasm_hash, ());
+        
+        // Initialize the market with the factory owner as oracle and the default
+        // fee policy, unless the caller set this market's fee explicitly
+        let token: Address = env.storage().instance().get(&DataKey::Token).unwrap();
+        let (default_fee_bps, fee_collector) = Self::default_fee(env);
+        let config = market::MarketConfig {
+            fee_bps: fee_override.unwrap_or(default_fee_bps),
+            fee_collector,
+            betting_deadline: 0,
+            oracles: Vec::new(env),
+            threshold: 0,
+            creator_fee_bps: 0,
+            creator: creator.clone(),
+            open_timeout: MARKET_OPEN_TIMEOUT,
+            max_user_stake: 0,
+            tags: Vec::new(env),
+            bet_cooldown: 0,
+            require_unique_titles: false,
+            claim_window: 0,
+            oracle_contract: None,
+            admin: None,
+            min_pool: 0,
+            payout_token: None,
+            payout_rate_bps: 0,
+            draw_fee_bps: 0,
+            min_bettors: 0,
+            emit_bet_events: true,
+            reopen_window: 0,
+            max_concentration_bps: 0,
+            bet_tick: 0,
+            max_imbalance_bps: 0,
+        };
+        market::Client::new(env, &market_address).initialize(
+            &livestream_ids,
+            &question,
+            &livestream_titles,
+            owner,
+            &env.current_contract_address(),
+            &token,
+            &config,
+        );
+        
+        // Store market info
+        env.storage().persistent().set(&DataKey::ValidMarkets(market_address.clone()), &true);
+        env.storage().persistent().set(&DataKey::MarketToLivestreams(market_address.clone()), &livestream_ids);
+        env.storage().persistent().set(&DataKey::MarketQuestion(market_address.clone()), &question);
+        env.storage().persistent().set(&DataKey::MarketState(market_address.clone()), &MARKET_STATE_OPEN);
+        env.storage().persistent().set(&DataKey::MarketCreator(market_address.clone()), creator);
+        env.storage().persistent().set(
+            &DataKey::QuestionHashToMarket(Self::question_hash(env, &question)),
+            &market_address
+        );
+        
+        let mut all_markets: Vec<Address> = env.storage().instance().get(&DataKey::AllMarkets)
+            .unwrap_or(Vec::<Address>::new(env));
+        all_markets.push_back(market_address.clone());
+        env.storage().instance().set(&DataKey::AllMarkets, &all_markets);
+        
+        // Add market to each livestream's market list
+        for i in 0..livestream_ids.len() {
+            let livestream_id = livestream_ids.get(i).unwrap();
+            
+            let mut markets = env.storage()
+                .persistent()
+                .get(&DataKey::LivestreamMarkets(livestream_id))
+                .unwrap_or(Vec::<Address>::new(env));
+            
+            markets.push_back(market_address.clone());
+            env.storage().persistent().set(&DataKey::LivestreamMarkets(livestream_id), &markets);
+        }
+        
+        env.events().publish(
+            (String::from_str(env, "market_created"),),
+            (market_address.clone(), question, livestream_ids.clone())
+        );
+        
+        market_address
     }
 }
 
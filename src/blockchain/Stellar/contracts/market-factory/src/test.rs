@@ -1,20 +1,1167 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{Address, BytesN, Env};
+use soroban_sdk::{
+    testutils::{Address as _, Events, Ledger, MockAuth, MockAuthInvoke},
+    token, Address, BytesN, Env, IntoVal, String, TryFromVal, Vec,
+};
+
+fn setup_factory<'a>(env: &Env) -> (MarketFactoryClient<'a>, Address, BytesN<32>, Address) {
+    // Deploying the market wasm alone is close to the default per-call budget
+    env.cost_estimate().budget().reset_unlimited();
+    let contract_id = env.register(MarketFactory, ());
+    let client = MarketFactoryClient::new(env, &contract_id);
+
+    let owner = Address::generate(env);
+    let token = env.register_stellar_asset_contract_v2(Address::generate(env)).address();
+    client.initialize(&owner, &owner, &token);
+
+    let wasm_hash = env.deployer().upload_contract_wasm(market::WASM);
+    (client, owner, wasm_hash, token)
+}
+
+fn create_market(
+    env: &Env,
+    client: &MarketFactoryClient,
+    owner: &Address,
+    wasm_hash: &BytesN<32>,
+    question: &str,
+    livestream_ids: Vec<u64>,
+) -> Address {
+    let mut livestream_titles = Vec::new(env);
+    for _ in livestream_ids.iter() {
+        livestream_titles.push_back(String::from_str(env, "Livestream"));
+    }
+    client.create_market(
+        owner,
+        &livestream_ids,
+        &String::from_str(env, question),
+        &livestream_titles,
+        wasm_hash,
+        &None,
+    )
+}
 
 #[test]
 fn test_initialize() {
     let env = Env::default();
+    env.mock_all_auths();
     let contract_id = env.register(MarketFactory, ());
     let client = MarketFactoryClient::new(&env, &contract_id);
 
-    // Create test address using BytesN
-    let owner_bytes = [1u8; 32];
-    let owner = Address::from_contract_id(&env, &BytesN::from_array(&env, &owner_bytes));
-    client.initialize(&owner);
+    let deployer = Address::generate(&env);
+    let owner = Address::generate(&env);
+    let token = Address::generate(&env);
+    assert!(!client.is_initialized());
+    client.initialize(&deployer, &owner, &token);
+    assert!(client.is_initialized());
     
     let retrieved_owner = client.get_owner();
     assert_eq!(owner, retrieved_owner);
 }
 
+#[test]
+fn test_initialize_twice() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(MarketFactory, ());
+    let client = MarketFactoryClient::new(&env, &contract_id);
+
+    let deployer = Address::generate(&env);
+    let owner = Address::generate(&env);
+    let token = Address::generate(&env);
+    client.initialize(&deployer, &owner, &token);
+    assert_eq!(
+        client.try_initialize(&deployer, &owner, &token),
+        Err(Ok(FactoryError::AlreadyInitialized.into()))
+    );
+}
+
+#[test]
+fn test_notify_market_closed_requires_market_auth() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64, 2u64]));
+
+    // A random account signs, but the call claims to come from the market
+    let attacker = Address::generate(&env);
+    let livestream_ids = Vec::from_array(&env, [1u64, 2u64]);
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &client.address,
+                fn_name: "notify_market_closed",
+                args: (market.clone(), livestream_ids.clone()).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_notify_market_closed(&market, &livestream_ids);
+    assert!(result.is_err());
+
+    // The same call authorized by the market itself goes through
+    client
+        .mock_auths(&[MockAuth {
+            address: &market,
+            invoke: &MockAuthInvoke {
+                contract: &client.address,
+                fn_name: "notify_market_closed",
+                args: (market.clone(), livestream_ids.clone()).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .notify_market_closed(&market, &livestream_ids);
+}
+
+#[test]
+fn test_get_market_summary() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let livestream_ids = Vec::from_array(&env, [1u64, 2u64, 3u64]);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", livestream_ids.clone());
+
+    let (question, livestream_count, state) = client.get_market_summary(&market);
+    assert_eq!(question, String::from_str(&env, "Who wins?"));
+    assert_eq!(livestream_count, 3);
+    assert_eq!(state, 0);
+
+    client.notify_market_closed(&market, &livestream_ids);
+    let (_, _, state) = client.get_market_summary(&market);
+    assert_eq!(state, 1);
+}
+
+#[test]
+fn test_error_codes() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64]));
+
+    let stranger = Address::generate(&env);
+    let title = String::from_str(&env, "Livestream");
+    let question = String::from_str(&env, "Another question?");
+
+    assert_eq!(
+        client.try_create_market(&stranger, &Vec::from_array(&env, [1u64]), &question, &Vec::from_array(&env, [title.clone()]), &wasm_hash, &None),
+        Err(Ok(FactoryError::NotOwner.into()))
+    );
+    assert_eq!(
+        client.try_create_market(&owner, &Vec::from_array(&env, [1u64, 2u64]), &question, &Vec::from_array(&env, [title.clone()]), &wasm_hash, &None),
+        Err(Ok(FactoryError::MismatchedArrays.into()))
+    );
+    assert_eq!(
+        client.try_add_livestream_to_market(&owner, &stranger, &2, &title),
+        Err(Ok(FactoryError::InvalidMarket.into()))
+    );
+    assert_eq!(
+        client.try_add_livestream_to_market(&owner, &market, &1, &title),
+        Err(Ok(FactoryError::LivestreamAlreadyInMarket.into()))
+    );
+    assert_eq!(
+        client.try_remove_livestream_from_market(&stranger, &market, &1),
+        Err(Ok(FactoryError::NotOwner.into()))
+    );
+}
+
+#[test]
+fn test_not_initialized() {
+    let env = Env::default();
+    let contract_id = env.register(MarketFactory, ());
+    let client = MarketFactoryClient::new(&env, &contract_id);
+
+    assert_eq!(client.try_get_owner(), Err(Ok(FactoryError::NotInitialized.into())));
+}
+
+#[test]
+fn test_create_markets_batch() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+
+    let title = String::from_str(&env, "Livestream");
+    let questions = Vec::from_array(
+        &env,
+        [
+            String::from_str(&env, "Who wins round 1?"),
+            String::from_str(&env, "Who wins round 2?"),
+            String::from_str(&env, "Who wins round 1?"),
+        ],
+    );
+    let livestream_ids_list = Vec::from_array(
+        &env,
+        [
+            Vec::from_array(&env, [1u64, 2u64]),
+            Vec::from_array(&env, [3u64]),
+            Vec::from_array(&env, [4u64]),
+        ],
+    );
+    let titles_list = Vec::from_array(
+        &env,
+        [
+            Vec::from_array(&env, [title.clone(), title.clone()]),
+            Vec::from_array(&env, [title.clone()]),
+            Vec::from_array(&env, [title.clone()]),
+        ],
+    );
+
+    let markets = client.create_markets(&owner, &questions, &livestream_ids_list, &titles_list, &wasm_hash);
+    assert_eq!(markets.len(), 3);
+    for market in markets.iter() {
+        assert!(client.is_valid_market(&market));
+    }
+    assert_eq!(client.get_total_market_count(), 3);
+    assert_eq!(client.get_all_markets(&0, &10), markets);
+    assert_eq!(client.get_livestreams_for_market(&markets.get(0).unwrap()), Vec::from_array(&env, [1u64, 2u64]));
+}
+
+#[test]
+fn test_create_markets_batch_limit() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+
+    let mut questions = Vec::new(&env);
+    let mut livestream_ids_list = Vec::new(&env);
+    let mut titles_list = Vec::new(&env);
+    for i in 0..=MAX_BATCH {
+        questions.push_back(String::from_str(&env, "Who wins?"));
+        livestream_ids_list.push_back(Vec::from_array(&env, [i as u64 + 1]));
+        titles_list.push_back(Vec::from_array(&env, [String::from_str(&env, "Livestream")]));
+    }
+
+    assert_eq!(
+        client.try_create_markets(&owner, &questions, &livestream_ids_list, &titles_list, &wasm_hash),
+        Err(Ok(FactoryError::BatchTooLarge.into()))
+    );
+
+    questions.pop_back();
+    livestream_ids_list.pop_back();
+    titles_list.pop_back();
+    let markets = client.create_markets(&owner, &questions, &livestream_ids_list, &titles_list, &wasm_hash);
+    assert_eq!(markets.len(), MAX_BATCH);
+}
+
+#[test]
+fn test_create_market_same_question() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+
+    let market1 = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64]));
+    let market2 = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64]));
+
+    assert_ne!(market1, market2);
+    assert!(client.is_valid_market(&market1));
+    assert!(client.is_valid_market(&market2));
+    assert_eq!(client.get_markets_for_livestream(&1), Vec::from_array(&env, [market1, market2]));
+}
+
+#[test]
+fn test_market_inherits_default_fee() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    assert_eq!(client.get_default_fee(), (0, owner.clone()));
+
+    let fee_collector = Address::generate(&env);
+    client.set_default_fee(&owner, &250, &fee_collector);
+    assert_eq!(client.get_default_fee(), (250, fee_collector.clone()));
+
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64, 2u64]));
+    let market_client = market::Client::new(&env, &market);
+    let (ids, question, _, _, _, _, _) = market_client.get_market_info();
+    assert_eq!(ids, Vec::from_array(&env, [1u64, 2u64]));
+    assert_eq!(question, String::from_str(&env, "Who wins?"));
+
+    // A sole winner of 1000 pays the inherited 2.5% fee
+    let bettor = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&bettor, &1000);
+    market_client.place_bet(&bettor, &1, &1000);
+    market_client.close_market(&owner);
+    market_client.resolve_market(&owner, &1);
+    market_client.claim_payout(&bettor);
+    assert_eq!(market_client.get_accrued_fees(), 25);
+
+    market_client.withdraw_fees(&fee_collector);
+    assert_eq!(token::Client::new(&env, &token).balance(&fee_collector), 25);
+}
+
+#[test]
+fn test_set_default_fee_too_high() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, _, _) = setup_factory(&env);
+
+    assert_eq!(
+        client.try_set_default_fee(&owner, &2001, &owner),
+        Err(Ok(FactoryError::FeeTooHigh.into()))
+    );
+}
+
+#[test]
+fn test_create_market_fee_override() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    client.set_default_fee(&owner, &250, &owner);
+
+    let ids = Vec::from_array(&env, [1u64, 2u64]);
+    let titles = Vec::from_array(&env, [String::from_str(&env, "A"), String::from_str(&env, "B")]);
+    let market = client.create_market(&owner, &ids, &String::from_str(&env, "q"), &titles, &wasm_hash, &Some(1000));
+    let market_client = market::Client::new(&env, &market);
+
+    // The explicit 10% replaces the 2.5% default
+    let bettor = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&bettor, &1000);
+    market_client.place_bet(&bettor, &1, &1000);
+    market_client.close_market(&owner);
+    market_client.resolve_market(&owner, &1);
+    market_client.claim_payout(&bettor);
+    assert_eq!(market_client.get_accrued_fees(), 100);
+
+    assert_eq!(
+        client.try_create_market(&owner, &ids, &String::from_str(&env, "q2"), &titles, &wasm_hash, &Some(2001)),
+        Err(Ok(FactoryError::FeeTooHigh.into()))
+    );
+}
+
+#[test]
+fn test_get_recent_markets() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+
+    let mut markets = Vec::new(&env);
+    for id in 1..=5u64 {
+        markets.push_back(create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [id])));
+    }
+
+    let first_page = client.get_recent_markets(&0, &2);
+    assert_eq!(first_page, Vec::from_array(&env, [markets.get(4).unwrap(), markets.get(3).unwrap()]));
+
+    let last_page = client.get_recent_markets(&4, &2);
+    assert_eq!(last_page, Vec::from_array(&env, [markets.get(0).unwrap()]));
+
+    assert_eq!(client.get_recent_markets(&5, &2).len(), 0);
+    assert_eq!(client.get_recent_markets(&10, &2).len(), 0);
+}
+
+#[test]
+fn test_get_all_markets_past_end() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    assert_eq!(client.get_all_markets(&0, &10).len(), 0);
+
+    let market1 = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64]));
+    let market2 = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [2u64]));
+
+    // Page through until an empty page comes back
+    assert_eq!(client.get_all_markets(&0, &1), Vec::from_array(&env, [market1]));
+    assert_eq!(client.get_all_markets(&1, &1), Vec::from_array(&env, [market2.clone()]));
+    assert_eq!(client.get_all_markets(&2, &1).len(), 0);
+    assert_eq!(client.get_all_markets(&3, &1).len(), 0);
+    assert_eq!(client.get_all_markets(&1, &u32::MAX), Vec::from_array(&env, [market2]));
+}
+
+#[test]
+fn test_market_close_notifies_factory() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64, 2u64]));
+
+    market::Client::new(&env, &market).close_market(&owner);
+
+    let closed_topic = String::from_str(&env, "market_closed");
+    let factory_closed_event = env.events().all().iter().any(|(contract, topics, _)| {
+        contract == client.address && String::try_from_val(&env, &topics.get(0).unwrap()) == Ok(closed_topic.clone())
+    });
+    assert!(factory_closed_event);
+    assert_eq!(client.get_market_summary(&market).2, 1);
+}
+
+#[test]
+fn test_global_stats_aggregate_closed_markets() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    assert_eq!(client.get_global_stats(), (0, 0));
+
+    let market_a = create_market(&env, &client, &owner, &wasm_hash, "Market A", Vec::from_array(&env, [1u64, 2u64]));
+    let market_b = create_market(&env, &client, &owner, &wasm_hash, "Market B", Vec::from_array(&env, [3u64, 4u64]));
+    let client_a = market::Client::new(&env, &market_a);
+    let client_b = market::Client::new(&env, &market_b);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&alice, &1000);
+    token_admin.mint(&bob, &1000);
+
+    client_a.place_bet(&alice, &1, &300);
+    client_a.place_bet(&bob, &2, &200);
+    client_b.place_bet(&alice, &3, &400);
+
+    client_a.close_market(&owner);
+    client_b.close_market(&owner);
+
+    assert_eq!(client.get_global_stats(), (900, 3));
+}
+
+#[test]
+fn test_report_stats_rejects_unknown_market() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, _, _) = setup_factory(&env);
+
+    let result = client.try_report_stats(&Address::generate(&env), &100, &1);
+    assert_eq!(result, Err(Ok(FactoryError::InvalidMarket.into())));
+}
+
+#[test]
+fn test_force_close_stuck_market() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64, 2u64]));
+    let market_client = market::Client::new(&env, &market);
+
+    let bettor = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&bettor, &1000);
+    market_client.place_bet(&bettor, &1, &400);
+
+    // Too early: the market is still within its open timeout
+    assert!(client.try_force_close_market(&owner, &market).is_err());
+
+    env.ledger().with_mut(|li| li.timestamp += MARKET_OPEN_TIMEOUT);
+    assert_eq!(
+        client.try_force_close_market(&Address::generate(&env), &market),
+        Err(Ok(FactoryError::NotOwner.into()))
+    );
+
+    client.force_close_market(&owner, &market);
+    assert_eq!(market_client.get_market_info().2, market::State::Closed);
+    assert_eq!(client.get_market_summary(&market).2, MARKET_STATE_CLOSED);
+    assert_eq!(client.get_global_stats(), (400, 1));
+}
+
+#[test]
+fn test_two_step_ownership_transfer() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, _, _) = setup_factory(&env);
+    let new_owner = Address::generate(&env);
+
+    assert_eq!(client.try_get_pending_owner(), Err(Ok(FactoryError::NoPendingOwner.into())));
+
+    client.transfer_ownership(&owner, &new_owner);
+    assert_eq!(client.get_pending_owner(), new_owner);
+    assert_eq!(client.get_owner(), owner);
+
+    let random = Address::generate(&env);
+    assert_eq!(client.try_accept_ownership(&random), Err(Ok(FactoryError::NotPendingOwner.into())));
+
+    client.accept_ownership(&new_owner);
+    assert_eq!(client.get_owner(), new_owner);
+    assert_eq!(client.try_get_pending_owner(), Err(Ok(FactoryError::NoPendingOwner.into())));
+}
+
+#[test]
+fn test_get_markets_for_livestreams_deduplicates() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let market_a = create_market(&env, &client, &owner, &wasm_hash, "Market A", Vec::from_array(&env, [1u64, 2u64]));
+    let market_b = create_market(&env, &client, &owner, &wasm_hash, "Market B", Vec::from_array(&env, [2u64, 3u64]));
+    create_market(&env, &client, &owner, &wasm_hash, "Market C", Vec::from_array(&env, [4u64, 5u64]));
+
+    let markets = client.get_markets_for_livestreams(&Vec::from_array(&env, [1u64, 2u64, 3u64]));
+    assert_eq!(markets, Vec::from_array(&env, [market_a, market_b]));
+
+    let mut too_many = Vec::new(&env);
+    for id in 1..=21u64 {
+        too_many.push_back(id);
+    }
+    assert_eq!(
+        client.try_get_markets_for_livestreams(&too_many),
+        Err(Ok(FactoryError::TooManyLivestreams.into()))
+    );
+}
+
+#[test]
+fn test_factory_pause_blocks_creation() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Before pause", Vec::from_array(&env, [1u64, 2u64]));
+
+    client.pause_factory(&owner);
+    let result = client.try_create_market(
+        &owner,
+        &Vec::from_array(&env, [3u64]),
+        &String::from_str(&env, "During pause"),
+        &Vec::from_array(&env, [String::from_str(&env, "Livestream")]),
+        &wasm_hash,
+        &None,
+    );
+    assert_eq!(result, Err(Ok(FactoryError::FactoryPaused.into())));
+    assert_eq!(
+        client.try_add_livestream_to_market(&owner, &market, &3, &String::from_str(&env, "Livestream")),
+        Err(Ok(FactoryError::FactoryPaused.into()))
+    );
+
+    client.unpause_factory(&owner);
+    assert_eq!(client.try_unpause_factory(&owner), Err(Ok(FactoryError::FactoryNotPaused.into())));
+    create_market(&env, &client, &owner, &wasm_hash, "After pause", Vec::from_array(&env, [3u64]));
+    client.add_livestream_to_market(&owner, &market, &3, &String::from_str(&env, "Livestream"));
+}
+
+#[test]
+fn test_get_claimable_markets() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let user = Address::generate(&env);
+    let other = Address::generate(&env);
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&user, &1000);
+    token_admin.mint(&other, &1000);
+
+    let mut markets = Vec::new(&env);
+    for question in ["Claimed", "Claimable", "Lost", "Still open"] {
+        let market = create_market(&env, &client, &owner, &wasm_hash, question, Vec::from_array(&env, [1u64, 2u64]));
+        let market_client = market::Client::new(&env, &market);
+        market_client.place_bet(&user, &1, &100);
+        market_client.place_bet(&other, &2, &100);
+        if question != "Still open" {
+            market_client.close_market(&owner);
+            market_client.resolve_market(&owner, &if question == "Lost" { 2 } else { 1 });
+        }
+        markets.push_back(market);
+    }
+    market::Client::new(&env, &markets.get(0).unwrap()).claim_payout(&user);
+    markets.push_back(Address::generate(&env));
+
+    let claimable = client.get_claimable_markets(&user, &markets);
+    assert_eq!(claimable, Vec::from_array(&env, [markets.get(1).unwrap()]));
+}
+
+#[test]
+fn test_get_claimable_markets_weighted() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Podium", Vec::from_array(&env, [1u64, 2u64, 3u64]));
+    let market_client = market::Client::new(&env, &market);
+
+    let runner_up = Address::generate(&env);
+    let loser = Address::generate(&env);
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&runner_up, &100);
+    token_admin.mint(&loser, &100);
+    market_client.place_bet(&runner_up, &2, &100);
+    market_client.place_bet(&loser, &3, &100);
+    market_client.close_market(&owner);
+    market_client.resolve_weighted(&owner, &Vec::from_array(&env, [1u64, 2u64]), &Vec::from_array(&env, [3u64, 1u64]));
+
+    // Livestream 2 pays out even though livestream 1 is the recorded winner
+    let markets = Vec::from_array(&env, [market.clone()]);
+    assert_eq!(client.get_claimable_markets(&runner_up, &markets), markets);
+    assert!(client.get_claimable_markets(&loser, &markets).is_empty());
+
+    market_client.claim_payout(&runner_up);
+    assert!(client.get_claimable_markets(&runner_up, &markets).is_empty());
+}
+
+#[test]
+fn test_get_claimable_markets_skips_expired_claims() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, _, token) = setup_factory(&env);
+
+    // Factory markets have no claim window, so register one set up by hand
+    let market = env.register(market::WASM, ());
+    let market_client = market::Client::new(&env, &market);
+    let config = market::MarketConfig {
+        fee_bps: 0,
+        fee_collector: owner.clone(),
+        betting_deadline: 0,
+        oracles: Vec::new(&env),
+        threshold: 0,
+        creator_fee_bps: 0,
+        creator: owner.clone(),
+        open_timeout: 0,
+        max_user_stake: 0,
+        tags: Vec::new(&env),
+        bet_cooldown: 0,
+        require_unique_titles: false,
+        claim_window: 100,
+        oracle_contract: None,
+        admin: None,
+        min_pool: 0,
+        payout_token: None,
+        payout_rate_bps: 0,
+        draw_fee_bps: 0,
+        min_bettors: 0,
+        emit_bet_events: true,
+        reopen_window: 0,
+        max_concentration_bps: 0,
+        bet_tick: 0,
+        max_imbalance_bps: 0,
+    };
+    market_client.initialize(
+        &Vec::from_array(&env, [1u64, 2u64]),
+        &String::from_str(&env, "Windowed"),
+        &Vec::from_array(&env, [String::from_str(&env, "A"), String::from_str(&env, "B")]),
+        &owner,
+        &client.address,
+        &token,
+        &config,
+    );
+    env.as_contract(&client.address, || {
+        env.storage().persistent().set(&DataKey::ValidMarkets(market.clone()), &true);
+    });
+
+    let user = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&user, &100);
+    market_client.place_bet(&user, &1, &100);
+    market_client.close_market(&owner);
+    market_client.resolve_market(&owner, &1);
+
+    let markets = Vec::from_array(&env, [market.clone()]);
+    assert_eq!(client.get_claimable_markets(&user, &markets), markets);
+    env.ledger().with_mut(|li| li.timestamp += 101);
+    assert!(client.get_claimable_markets(&user, &markets).is_empty());
+}
+
+#[test]
+fn test_featured_markets() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let market_a = create_market(&env, &client, &owner, &wasm_hash, "Market A", Vec::from_array(&env, [1u64]));
+    let market_b = create_market(&env, &client, &owner, &wasm_hash, "Market B", Vec::from_array(&env, [2u64]));
+    let market_c = create_market(&env, &client, &owner, &wasm_hash, "Market C", Vec::from_array(&env, [3u64]));
+
+    client.set_featured(&owner, &market_a, &true);
+    client.set_featured(&owner, &market_b, &true);
+    client.set_featured(&owner, &market_c, &true);
+    client.set_featured(&owner, &market_b, &false);
+    // Featuring twice doesn't duplicate the entry
+    client.set_featured(&owner, &market_c, &true);
+
+    assert_eq!(client.get_featured_markets(&0, &10), Vec::from_array(&env, [market_a.clone(), market_c.clone()]));
+    assert_eq!(client.get_featured_markets(&1, &10), Vec::from_array(&env, [market_c]));
+    assert_eq!(
+        client.try_set_featured(&Address::generate(&env), &market_a, &false),
+        Err(Ok(FactoryError::NotOwner.into()))
+    );
+}
+
+#[test]
+fn test_filter_valid_markets() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let market_a = create_market(&env, &client, &owner, &wasm_hash, "Market A", Vec::from_array(&env, [1u64]));
+    let market_b = create_market(&env, &client, &owner, &wasm_hash, "Market B", Vec::from_array(&env, [2u64]));
+
+    let candidates = Vec::from_array(
+        &env,
+        [Address::generate(&env), market_a.clone(), Address::generate(&env), market_b.clone()],
+    );
+    assert_eq!(client.filter_valid_markets(&candidates), Vec::from_array(&env, [market_a, market_b]));
+
+    let mut too_many = Vec::new(&env);
+    for _ in 0..21 {
+        too_many.push_back(Address::generate(&env));
+    }
+    assert_eq!(client.try_filter_valid_markets(&too_many), Err(Ok(FactoryError::TooManyMarkets.into())));
+}
+
+#[test]
+fn test_market_creator_recorded() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64, 2u64]));
+
+    assert_eq!(client.get_market_creator(&market), owner);
+    assert_eq!(market::Client::new(&env, &market).get_creator(), owner);
+    assert_eq!(
+        client.try_get_market_creator(&Address::generate(&env)),
+        Err(Ok(FactoryError::InvalidMarket.into()))
+    );
+}
+
+#[test]
+fn test_emergency_withdraw_timelock() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64, 2u64]));
+    let market_client = market::Client::new(&env, &market);
+    let token_client = token::Client::new(&env, &token);
+
+    let bettor = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&bettor, &1000);
+    market_client.place_bet(&bettor, &1, &1000);
+
+    let rescue = Address::generate(&env);
+    assert_eq!(
+        market_client.try_emergency_withdraw(&owner, &rescue),
+        Err(Ok(market::MarketError::EmergencyNotEnabled.into()))
+    );
+    assert_eq!(
+        market_client.try_enable_emergency(&bettor),
+        Err(Ok(market::MarketError::NotFactoryOwner.into()))
+    );
+
+    market_client.enable_emergency(&owner);
+    env.ledger().with_mut(|li| li.timestamp += 6 * 24 * 60 * 60);
+    assert_eq!(
+        market_client.try_emergency_withdraw(&owner, &rescue),
+        Err(Ok(market::MarketError::EmergencyTimelocked.into()))
+    );
+
+    env.ledger().with_mut(|li| li.timestamp += 24 * 60 * 60);
+    market_client.emergency_withdraw(&owner, &rescue);
+    assert_eq!(token_client.balance(&rescue), 1000);
+    assert_eq!(token_client.balance(&market), 0);
+}
+
+#[test]
+fn test_find_market_by_question() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64, 2u64]));
+
+    assert_eq!(client.find_market_by_question(&String::from_str(&env, "Who wins?")), Some(market));
+    assert_eq!(client.find_market_by_question(&String::from_str(&env, "Who wins")), None);
+}
+
+#[test]
+fn test_get_market_details() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64, 2u64, 3u64]));
+
+    let bettor = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&bettor, &300);
+    let market_client = market::Client::new(&env, &market);
+    market_client.place_bet(&bettor, &1, &100);
+    market_client.place_bet(&bettor, &2, &200);
+
+    assert_eq!(
+        client.get_market_details(&market),
+        (String::from_str(&env, "Who wins?"), MARKET_STATE_OPEN, 3, 300, 1)
+    );
+    assert_eq!(
+        client.try_get_market_details(&Address::generate(&env)),
+        Err(Ok(FactoryError::InvalidMarket.into()))
+    );
+}
+
+#[test]
+fn test_predict_market_address() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    create_market(&env, &client, &owner, &wasm_hash, "Earlier market", Vec::from_array(&env, [1u64]));
+
+    let predicted = client.predict_market_address(&String::from_str(&env, "Who wins?"));
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64, 2u64]));
+    assert_eq!(predicted, market);
+
+    // The nonce moved on, so the same question now maps elsewhere
+    assert_ne!(client.predict_market_address(&String::from_str(&env, "Who wins?")), market);
+}
+
+#[test]
+fn test_get_markets_info() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market1 = create_market(&env, &client, &owner, &wasm_hash, "First?", Vec::from_array(&env, [1u64]));
+    let market2 = create_market(&env, &client, &owner, &wasm_hash, "Second?", Vec::from_array(&env, [2u64]));
+
+    let bettor = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&bettor, &50);
+    market::Client::new(&env, &market2).place_bet(&bettor, &2, &50);
+    market::Client::new(&env, &market1).close_market(&owner);
+
+    let markets = Vec::from_array(&env, [market1.clone(), Address::generate(&env), market2.clone()]);
+    assert_eq!(
+        client.get_markets_info(&markets),
+        Vec::from_array(&env, [(market1, MARKET_STATE_CLOSED, 0i128), (market2, MARKET_STATE_OPEN, 50i128)])
+    );
+}
+
+#[test]
+fn test_reopened_market_withdraws_stats() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Who wins?", Vec::from_array(&env, [1u64, 2u64]));
+    let market_client = market::Client::new(&env, &market);
+
+    let bettor = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&bettor, &500);
+    market_client.place_bet(&bettor, &1, &200);
+    market_client.close_market(&owner);
+    assert_eq!(client.get_global_stats(), (200, 1));
+
+    // Factory markets have no reopen window, so simulate the market's callback
+    client.notify_market_reopened(&market, &200, &1);
+    assert_eq!(client.get_global_stats(), (0, 0));
+    assert_eq!(client.get_market_summary(&market).2, MARKET_STATE_OPEN);
+}
+
+#[test]
+fn test_set_market_livestreams() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "Bracket", Vec::from_array(&env, [1u64, 2u64]));
+    let other = create_market(&env, &client, &owner, &wasm_hash, "Other", Vec::from_array(&env, [1u64]));
+
+    client.set_market_livestreams(&owner, &market, &Vec::from_array(&env, [2u64, 3u64]));
+
+    assert_eq!(client.get_livestreams_for_market(&market), Vec::from_array(&env, [2u64, 3u64]));
+    assert_eq!(client.get_markets_for_livestream(&1), Vec::from_array(&env, [other]));
+    assert_eq!(client.get_markets_for_livestream(&2), Vec::from_array(&env, [market.clone()]));
+    assert_eq!(client.get_markets_for_livestream(&3), Vec::from_array(&env, [market.clone()]));
+
+    assert_eq!(
+        client.try_set_market_livestreams(&owner, &market, &Vec::from_array(&env, [4u64, 4u64])),
+        Err(Ok(FactoryError::LivestreamAlreadyInMarket.into()))
+    );
+}
+
+#[test]
+fn test_fork_market() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let source = create_market(&env, &client, &owner, &wasm_hash, "Bracket", Vec::from_array(&env, [1u64, 2u64, 3u64]));
+    let source_client = market::Client::new(&env, &source);
+    let token_client = token::Client::new(&env, &token);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&alice, &1000);
+    token_admin.mint(&bob, &1000);
+    source_client.place_bet(&alice, &1, &100);
+    source_client.place_bet(&alice, &2, &300);
+    source_client.place_bet(&bob, &3, &200);
+
+    let forked = client.fork_market(
+        &owner,
+        &source,
+        &Vec::from_array(&env, [2u64, 3u64]),
+        &String::from_str(&env, "Second bracket"),
+        &wasm_hash,
+    );
+    let forked_client = market::Client::new(&env, &forked);
+
+    // Bets and their tokens followed the livestreams
+    assert_eq!(forked_client.get_user_bet(&alice, &2), 300);
+    assert_eq!(forked_client.get_user_bet(&bob, &3), 200);
+    assert_eq!(source_client.get_user_bet(&alice, &1), 100);
+    assert_eq!(source_client.get_user_bet(&alice, &2), 0);
+    assert_eq!(source_client.get_market_info().4, 100);
+    assert_eq!(forked_client.get_market_info().4, 500);
+    assert_eq!(token_client.balance(&source), 100);
+    assert_eq!(token_client.balance(&forked), 500);
+
+    // Registries point each livestream at its new market
+    assert_eq!(client.get_livestreams_for_market(&source), Vec::from_array(&env, [1u64]));
+    assert_eq!(client.get_livestreams_for_market(&forked), Vec::from_array(&env, [2u64, 3u64]));
+    assert_eq!(client.get_markets_for_livestream(&2), Vec::from_array(&env, [forked.clone()]));
+    assert_eq!(client.get_markets_for_livestream(&1), Vec::from_array(&env, [source.clone()]));
+
+    // Bob's whole stake moved, so only Alice still counts in the source
+    assert_eq!(source_client.get_market_info().5, 1);
+    assert_eq!(source_client.get_bettors(&0, &10), Vec::from_array(&env, [alice.clone()]));
+    assert_eq!(forked_client.get_market_info().5, 2);
+
+    // Moved livestreams stay behind as frozen tombstones instead of being recreated by bets
+    assert!(!source_client.get_livestream(&2).active);
+    assert!(source_client.try_place_bet(&bob, &2, &10).is_err());
+    assert_eq!(source_client.get_livestream_count(), 1);
+
+    let too_many = Vec::from_array(&env, [1u64; 21]);
+    assert_eq!(
+        client.try_fork_market(&owner, &source, &too_many, &String::from_str(&env, "q"), &wasm_hash),
+        Err(Ok(FactoryError::TooManyLivestreams.into()))
+    );
+
+    // The forked market settles like any other
+    forked_client.close_market(&owner);
+    forked_client.resolve_market(&owner, &3);
+    forked_client.claim_payout(&bob);
+    assert_eq!(token_client.balance(&bob), 1300);
+}
+
+#[test]
+fn test_fork_market_across_pages() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let source = create_market(&env, &client, &owner, &wasm_hash, "Bracket", Vec::from_array(&env, [1u64, 2u64]));
+    let source_client = market::Client::new(&env, &source);
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+
+    let alice = Address::generate(&env);
+    token_admin.mint(&alice, &200);
+    source_client.place_bet(&alice, &1, &100);
+    source_client.place_bet(&alice, &2, &100);
+    let mut backers: Vec<Address> = Vec::new(&env);
+    for _ in 0..60 {
+        let backer = Address::generate(&env);
+        token_admin.mint(&backer, &10);
+        source_client.place_bet(&backer, &2, &10);
+        backers.push_back(backer);
+    }
+
+    let forked = client.fork_market(
+        &owner,
+        &source,
+        &Vec::from_array(&env, [2u64]),
+        &String::from_str(&env, "Second bracket"),
+        &wasm_hash,
+    );
+    let forked_client = market::Client::new(&env, &forked);
+
+    // The pool moved at once, the bets of the last page are still to come
+    assert_eq!(client.get_pending_fork(&source), Some(forked.clone()));
+    assert_eq!(source_client.get_market_info().4, 100);
+    assert_eq!(source_client.get_market_info().5, 11);
+    assert_eq!(forked_client.get_market_info().5, 50);
+    assert_eq!(source_client.try_exit_market(&alice), Err(Ok(market::ExtMarketError::ForkPending.into())));
+
+    assert_eq!(client.continue_fork(&owner, &source, &20), 0);
+    assert_eq!(client.get_pending_fork(&source), None);
+    assert_eq!(
+        client.try_continue_fork(&owner, &source, &20),
+        Err(Ok(FactoryError::NoPendingFork.into()))
+    );
+
+    assert_eq!(source_client.get_market_info().5, 1);
+    assert_eq!(source_client.get_user_bet(&alice, &1), 100);
+    assert_eq!(forked_client.get_market_info().4, 700);
+    assert_eq!(forked_client.get_market_info().5, 61);
+    assert_eq!(forked_client.get_user_bet(&alice, &2), 100);
+    for backer in backers.iter() {
+        assert_eq!(forked_client.get_user_bet(&backer, &2), 10);
+    }
+}
+
+#[test]
+fn test_verify_market_factory() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+
+    let market = create_market(&env, &client, &owner, &wasm_hash, "q", Vec::from_array(&env, [1u64, 2u64]));
+    assert!(client.verify_market_factory(&market));
+
+    assert!(!client.verify_market_factory(&Address::generate(&env)));
+}
+
+#[test]
+fn test_get_livestream_bets_bps() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "q", Vec::from_array(&env, [1u64, 2u64]));
+    let market_client = market::Client::new(&env, &market);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&user1, &7);
+    token_admin.mint(&user2, &993);
+    market_client.place_bet(&user1, &1, &7);
+    market_client.place_bet(&user2, &2, &993);
+
+    assert_eq!(market_client.get_livestream_bets(&1), (7, 0, true));
+    assert_eq!(client.get_livestream_bets_bps(&market, &1), (7, 70, true));
+    assert_eq!(client.get_livestream_bets_bps(&market, &2), (993, 9930, true));
+    assert_eq!(
+        client.try_get_livestream_bets_bps(&Address::generate(&env), &1),
+        Err(Ok(FactoryError::InvalidMarket.into()))
+    );
+}
+
+#[test]
+fn test_get_top_bettor() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "q", Vec::from_array(&env, [1u64, 2u64]));
+    let market_client = market::Client::new(&env, &market);
+    assert_eq!(client.get_top_bettor(&market, &1), (market.clone(), 0));
+
+    let small = Address::generate(&env);
+    let whale = Address::generate(&env);
+    let medium = Address::generate(&env);
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    for user in [&small, &whale, &medium] {
+        token_admin.mint(user, &1000);
+    }
+    market_client.place_bet(&small, &1, &50);
+    market_client.place_bet(&whale, &1, &700);
+    market_client.place_bet(&medium, &1, &250);
+    market_client.place_bet(&medium, &2, &600);
+
+    assert_eq!(client.get_top_bettor(&market, &1), (whale, 700));
+    assert_eq!(client.get_top_bettor(&market, &2), (medium, 600));
+}
+
+#[test]
+fn test_get_user_position_value() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market = client.create_market(
+        &owner,
+        &Vec::from_array(&env, [1u64, 2u64]),
+        &String::from_str(&env, "q"),
+        &Vec::from_array(&env, [String::from_str(&env, "A"), String::from_str(&env, "B")]),
+        &wasm_hash,
+        &Some(1000),
+    );
+    let market_client = market::Client::new(&env, &market);
+
+    let user = Address::generate(&env);
+    let other = Address::generate(&env);
+    let sponsor = Address::generate(&env);
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    token_admin.mint(&user, &400);
+    token_admin.mint(&other, &600);
+    token_admin.mint(&sponsor, &500);
+    assert_eq!(client.get_user_position_value(&market, &user), 0);
+
+    market_client.place_bet(&user, &1, &100);
+    market_client.place_bet(&user, &2, &300);
+    market_client.place_bet(&other, &1, &300);
+    market_client.place_bet(&other, &2, &300);
+
+    // Livestream 1: 400 staked, pays 100 * 1000 / 400 = 250 with probability 0.4 -> 100
+    // Livestream 2: 600 staked, pays 300 * 1000 / 600 = 500 with probability 0.6 -> 300
+    // 400 gross, minus the 10% platform fee
+    assert_eq!(client.get_user_position_value(&market, &user), 360);
+
+    // A 500 bonus lifts every outcome by half
+    market_client.add_bonus_pool(&sponsor, &500);
+    assert_eq!(client.get_user_position_value(&market, &user), 540);
+}
+
+#[test]
+fn test_get_bettor_rank() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "q", Vec::from_array(&env, [1u64, 2u64]));
+    let market_client = market::Client::new(&env, &market);
+
+    let small = Address::generate(&env);
+    let large = Address::generate(&env);
+    let medium = Address::generate(&env);
+    let tied = Address::generate(&env);
+    let other = Address::generate(&env);
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    for user in [&small, &large, &medium, &tied, &other] {
+        token_admin.mint(user, &500);
+    }
+    market_client.place_bet(&small, &1, &100);
+    market_client.place_bet(&large, &1, &500);
+    market_client.place_bet(&medium, &1, &300);
+    market_client.place_bet(&other, &2, &400);
+
+    assert_eq!(client.get_bettor_rank(&market, &large, &1), (1, 3));
+    assert_eq!(client.get_bettor_rank(&market, &medium, &1), (2, 3));
+    assert_eq!(client.get_bettor_rank(&market, &small, &1), (3, 3));
+    assert_eq!(client.get_bettor_rank(&market, &other, &1), (0, 3));
+
+    // An equal stake placed later ranks behind
+    market_client.place_bet(&tied, &1, &300);
+    assert_eq!(client.get_bettor_rank(&market, &medium, &1), (2, 4));
+    assert_eq!(client.get_bettor_rank(&market, &tied, &1), (3, 4));
+}
+
+#[test]
+fn test_bettor_rank_ties_survive_exit() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "q", Vec::from_array(&env, [1u64, 2u64]));
+    let market_client = market::Client::new(&env, &market);
+
+    let leaver = Address::generate(&env);
+    let early = Address::generate(&env);
+    let late = Address::generate(&env);
+    let token_admin = token::StellarAssetClient::new(&env, &token);
+    for user in [&leaver, &early, &late] {
+        token_admin.mint(user, &300);
+    }
+    market_client.place_bet(&leaver, &2, &100);
+    env.ledger().with_mut(|li| li.timestamp += 10);
+    market_client.place_bet(&early, &1, &300);
+    env.ledger().with_mut(|li| li.timestamp += 10);
+    market_client.place_bet(&late, &1, &300);
+
+    // The exit moves the later bettor into the first slot of the bettor list
+    market_client.exit_market(&leaver);
+    assert_eq!(market_client.get_bettors(&0, &1), Vec::from_array(&env, [late.clone()]));
+    assert_eq!(client.get_bettor_rank(&market, &early, &1), (1, 2));
+    assert_eq!(client.get_bettor_rank(&market, &late, &1), (2, 2));
+}
+
+#[test]
+fn test_get_group_totals() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, token) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "q", Vec::from_array(&env, [1u64, 2u64, 3u64]));
+    let market_client = market::Client::new(&env, &market);
+
+    let user = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&user, &600);
+    market_client.place_bet(&user, &1, &100);
+    market_client.place_bet(&user, &2, &200);
+    market_client.place_bet(&user, &3, &300);
+
+    market_client.set_group(&owner, &1, &7);
+    market_client.set_group(&owner, &3, &7);
+    market_client.set_group(&owner, &2, &8);
+    assert_eq!(client.get_group_totals(&market), Vec::from_array(&env, [(7u64, 400i128), (8u64, 200i128)]));
+
+    market_client.set_group(&owner, &2, &0);
+    assert_eq!(client.get_group_totals(&market), Vec::from_array(&env, [(7u64, 400i128)]));
+}
+
+#[test]
+fn test_livestream_exists() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, owner, wasm_hash, _) = setup_factory(&env);
+    let market = create_market(&env, &client, &owner, &wasm_hash, "q", Vec::from_array(&env, [1u64, 2u64]));
+
+    assert_eq!(client.livestream_exists(&market, &1), (true, true));
+    assert_eq!(client.livestream_exists(&market, &99), (false, false));
+
+    market::Client::new(&env, &market).remove_livestream(&owner, &2);
+    assert_eq!(client.livestream_exists(&market, &2), (true, false));
+    assert_eq!(
+        client.try_livestream_exists(&Address::generate(&env), &1),
+        Err(Ok(FactoryError::InvalidMarket.into()))
+    );
+}
@@ -1,15 +1,97 @@
 #![no_std]
+// Events are still emitted through `Events::publish`, which SDK 23 marks deprecated.
+#![allow(deprecated)]
 
 use soroban_sdk::{
-    contract, contractimpl, contracttype, token, Address, Env, String, Vec
+    assert_with_error, contract, contractclient, contracterror, contractimpl, contracttype,
+    panic_with_error, token, Address, Bytes, Env, String, Vec
 };
 
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum MarketError {
+    AlreadyInitialized = 1,
+    MismatchedArrays = 2,
+    InvalidLivestream = 3,
+    NotOracle = 4,
+    NotOpen = 5,
+    NotClosed = 6,
+    NotResolved = 7,
+    LivestreamExists = 8,
+    LivestreamNotFound = 9,
+    LivestreamNotActive = 10,
+    EmptyTitle = 11,
+    InvalidAmount = 12,
+    DeadlinePassed = 13,
+    NoDeadline = 14,
+    DeadlineNotExtended = 15,
+    SelfReferral = 16,
+    NoBetsOnLivestream = 17,
+    NoWinningBet = 18,
+    FeeTooHigh = 19,
+    NotFeeCollector = 20,
+    NoFees = 21,
+    Paused = 22,
+    NotPaused = 23,
+    InvalidThreshold = 24,
+    MultiOracleMarket = 25,
+    NotFactory = 26,
+    OpenTimeoutNotReached = 27,
+    UserStakeCapExceeded = 28,
+    CannotRescueBettingToken = 29,
+    TooManyTags = 30,
+    InvalidTag = 31,
+    BetCooldownActive = 32,
+    SlippageExceeded = 33,
+    InsufficientPool = 34,
+    LivestreamActive = 35,
+    NotABettor = 36,
+    DuplicateTitle = 37,
+    InvalidWeights = 38,
+    ClaimWindowOpen = 39,
+    ClaimDeadlinePassed = 40,
+    NothingToSweep = 41,
+    NoOracleContract = 42,
+    NotAdmin = 43,
+    PoolBelowMinimum = 44,
+    NotCancelled = 45,
+    CannotCancel = 46,
+    NotFactoryOwner = 47,
+    EmergencyNotEnabled = 48,
+    EmergencyTimelocked = 49,
+    InvalidPayoutRate = 50,
+}
+
+// Overflow for `MarketError`, which is at the 50-variant limit of a contract type
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum ExtMarketError {
+    NotEnoughBettors = 51,
+    LivestreamFrozen = 52,
+    ReopenWindowClosed = 53,
+    ConcentrationExceeded = 54,
+    WinnerNotInMarket = 55,
+    AmountNotMultipleOfTick = 56,
+    UnclaimedFunds = 57,
+    BookUnbalanced = 58,
+    NoReleasedCollateral = 59,
+    MarketSettled = 60,
+    ClaimsOutstanding = 61,
+    ForkPending = 62,
+    ForkOutOfOrder = 63,
+    CleanupOutOfOrder = 64,
+}
+
 #[derive(Clone, Copy, PartialEq, Debug)]
 #[contracttype]
 pub enum State {
     Open = 0,
     Closed = 1,
     Resolved = 2,
+    Cancelled = 3,
+    Draw = 4,
 }
 
 #[contracttype]
@@ -21,6 +103,64 @@ pub struct LivestreamData {
     pub added_at: u64,
 }
 
+/// Optional market settings fixed at initialization
+#[contracttype]
+#[derive(Clone)]
+pub struct MarketConfig {
+    pub fee_bps: u32,
+    pub fee_collector: Address,
+    pub betting_deadline: u64, // 0 = no deadline
+    pub oracles: Vec<Address>, // empty = resolved by the single oracle
+    pub threshold: u32, // agreeing votes needed from `oracles`
+    pub creator_fee_bps: u32,
+    pub creator: Address,
+    pub open_timeout: u64, // seconds after creation the factory may force-close; 0 = never
+    pub max_user_stake: i128, // cap on a user's stake across all livestreams; 0 = no cap
+    pub tags: Vec<String>, // free-form labels for directory filtering
+    pub bet_cooldown: u64, // minimum seconds between a user's bets; 0 = none
+    pub require_unique_titles: bool, // reject a title already used by an active livestream
+    pub claim_window: u64, // seconds after resolution winners have to claim; 0 = forever
+    pub oracle_contract: Option<Address>, // data feed consulted by `auto_resolve`
+    pub admin: Option<Address>, // manages livestreams and pausing; None = the oracle does
+    pub min_pool: i128, // smallest total pool that can be resolved; 0 = no minimum
+    pub payout_token: Option<Address>, // token winners are paid in; None = the betting token
+    pub payout_rate_bps: u32, // payout tokens per betting token, in bps; used with `payout_token`
+    pub draw_fee_bps: u32, // processing fee kept from refunds after a draw
+    pub min_bettors: u64, // fewest distinct bettors a market can be resolved with; 0 = no minimum
+    pub emit_bet_events: bool, // false skips `bet_placed` events; lifecycle events always fire
+    pub reopen_window: u64, // seconds after closing the oracle may reopen; 0 = never
+    pub max_concentration_bps: u32, // largest share of the pool one livestream may hold; 0 = no cap
+    pub bet_tick: i128, // bets must be a multiple of this; 0 = any amount
+    pub max_imbalance_bps: u32, // how far one livestream may exceed all others combined; 0 = no cap
+}
+
+// Cap on platform + creator fees combined
+const MAX_FEE_BPS: u32 = 2000;
+
+// Largest shortfall a claim absorbs before treating it as an accounting bug
+const PAYOUT_ROUNDING_TOLERANCE: i128 = 100;
+
+// Fixed-point scale of the payout multiplier
+const PAYOUT_SCALE: i128 = 1_000_000_000;
+
+// Delay between arming and using the emergency withdrawal
+const EMERGENCY_TIMELOCK: u64 = 7 * 24 * 60 * 60;
+
+// Bets kept in the `RecentBets` ring buffer
+const RECENT_BETS_SIZE: u32 = 20;
+
+// Bettors per persistent storage page
+const BETTOR_PAGE_SIZE: u32 = 50;
+
+// Limits on market tags
+const MAX_TAGS: u32 = 10;
+const MAX_TAG_LEN: u32 = 32;
+
+// Roles returned by `get_role`
+const ROLE_SPECTATOR: u32 = 0;
+const ROLE_BETTOR: u32 = 1;
+const ROLE_ORACLE: u32 = 2;
+
 #[contracttype]
 pub enum DataKey {
     Question,
@@ -37,8 +177,90 @@ pub enum DataKey {
     TotalBets(u64), // livestream_id -> total amount
     TotalPool,
     HasBet(Address),
-    Bettors,
     TotalBettors,
+    Token,
+    Referrer(Address), // user -> referrer credited on their first bet
+    ReferralVolume(Address), // referrer -> total amount bet by referred users
+    FeeBps,
+    FeeCollector,
+    AccruedFees,
+    BettingDeadline,
+    Paused,
+    BetSeq,
+    ClosedSnapshot(u64), // livestream_id -> TotalBets frozen at close
+    Oracles,
+    Threshold,
+    ResolutionVote(Address), // oracle -> livestream_id voted as winner
+    CreatorFeeBps,
+    Creator,
+    OpenTimeout,
+    MaxUserStake,
+    Tags,
+    ResolutionProof, // opaque oracle evidence anchored for disputes
+    BetCooldown,
+    LastBetTime(Address), // user -> timestamp of their latest bet
+    RemovedIds, // livestreams currently removed, in removal order
+    RequireUniqueTitles,
+    BettorPage(u32), // page number -> up to BETTOR_PAGE_SIZE bettors
+    BettorIndex(Address), // bettor -> position across all pages
+    WeightedIds, // livestreams paid out by resolve_weighted
+    ResolutionWeight(u64), // livestream_id -> payout weight
+    ClaimWindow,
+    ClaimDeadline, // resolved_at + claim window, set on resolution
+    OracleContract,
+    Admin,
+    MinPool,
+    Version, // bumped by every state-changing call
+    EmergencyEnabled, // when the factory owner armed emergency_withdraw
+}
+
+// Overflow for `DataKey`, which is at the 50-variant limit of a contract type
+#[contracttype]
+pub enum ExtDataKey {
+    PayoutToken, // token winners are paid in, if not the betting token
+    PayoutRateBps, // payout tokens per betting token, in bps
+    FeeExempt(Address), // user -> true when their claims pay no fees
+    DrawFeeBps, // processing fee kept from refunds after a draw
+    Resolver, // address whose call resolved the market
+    LivestreamGroup(u64), // livestream_id -> group (team) id
+    MinBettors, // fewest distinct bettors needed to resolve
+    LivestreamFrozen(u64), // livestream_id -> true while betting on it is frozen
+    RefundableLivestream(u64), // removed livestream_id -> true if its stakes were refunded at resolution
+    TotalFees, // platform + creator fees taken by all claims, withdrawn or not
+    EmitBetEvents, // whether bets publish `bet_placed`
+    ReopenWindow, // seconds after closing the oracle may reopen
+    LivestreamBettorCount(u64), // livestream_id -> users with a stake on it
+    ClaimedCount, // successful payout claims
+    TotalWinners, // winning bettors counted at resolution
+    MaxConcentrationBps, // largest share of the pool one livestream may hold
+    TokenDecimals, // betting token decimals, cached for display
+    PayoutMultiplier, // total_pool * PAYOUT_SCALE / winning_pool, frozen at resolution
+    AutoClaim(Address), // user -> true if `process_auto_claims` may pay them
+    BetTick, // bets must be a multiple of this amount
+    MaxImbalanceBps, // how far one livestream's pool may exceed all others combined
+    RecentBets(u32), // ring buffer slot -> (user, livestream_id, amount, timestamp)
+    ReleasedCollateral, // betting tokens replaced by payout-token claims, owed to the fee collector
+    FirstBet(Address), // bettor -> (timestamp, bet sequence) when they joined
+    PaidOut, // gross winnings paid by claims, before fees
+    ForkProgress, // (livestream_ids, destination, bettors checked, bettors left) of an unfinished fork
+    CleanupCursor, // bettors already cleaned by `cleanup`
+}
+
+/// Callbacks the market makes into the factory that created it
+#[allow(dead_code)]
+#[contractclient(name = "FactoryClient")]
+trait FactoryInterface {
+    fn notify_market_closed(env: Env, caller: Address, livestream_ids: Vec<u64>);
+    fn report_stats(env: Env, caller_market: Address, pool: i128, bettors: u64);
+    fn notify_market_reopened(env: Env, caller_market: Address, pool: i128, bettors: u64);
+    fn get_owner(env: Env) -> Address;
+}
+
+/// On-chain data feed a market can resolve from
+#[allow(dead_code)]
+#[contractclient(name = "DataFeedClient")]
+trait DataFeedInterface {
+    fn get_winner(env: Env, market: Address) -> u64;
 }
 
 #[contract]
@@ -47,6 +269,7 @@ pub struct PredictionMarket;
 #[contractimpl]
 impl PredictionMarket {
     /// Initialize the prediction market
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
         env: Env,
         livestream_ids: Vec<u64>,
@@ -54,22 +277,85 @@ impl PredictionMarket {
         livestream_titles: Vec<String>,
         oracle: Address,
         factory: Address,
+        token: Address,
+        config: MarketConfig,
     ) {
+        let initialized = env.storage().instance().has(&DataKey::State);
+        assert_with_error!(&env, !initialized, MarketError::AlreadyInitialized);
+        
         // Ensure arrays match
-        assert!(
+        assert_with_error!(
+            &env,
             livestream_ids.len() == livestream_titles.len(),
-            "Mismatched arrays"
+            MarketError::MismatchedArrays
         );
+        assert_with_error!(
+            &env,
+            config.fee_bps + config.creator_fee_bps <= MAX_FEE_BPS,
+            MarketError::FeeTooHigh
+        );
+        assert_with_error!(
+            &env,
+            config.threshold <= config.oracles.len() && (config.oracles.is_empty() || config.threshold > 0),
+            MarketError::InvalidThreshold
+        );
+        assert_with_error!(&env, config.tags.len() <= MAX_TAGS, MarketError::TooManyTags);
+        for tag in config.tags.iter() {
+            assert_with_error!(
+                &env,
+                !tag.is_empty() && tag.len() <= MAX_TAG_LEN,
+                MarketError::InvalidTag
+            );
+        }
+        assert_with_error!(
+            &env,
+            config.payout_token.is_none() || config.payout_rate_bps > 0,
+            MarketError::InvalidPayoutRate
+        );
+        assert_with_error!(&env, config.draw_fee_bps <= MAX_FEE_BPS, MarketError::FeeTooHigh);
 
         // Store basic info
         env.storage().instance().set(&DataKey::Question, &question);
         env.storage().instance().set(&DataKey::Oracle, &oracle);
         env.storage().instance().set(&DataKey::Factory, &factory);
+        env.storage().instance().set(&DataKey::Token, &token);
+        env.storage().instance().set(&ExtDataKey::TokenDecimals, &token::Client::new(&env, &token).decimals());
         env.storage().instance().set(&DataKey::State, &State::Open);
         env.storage().instance().set(&DataKey::CreatedAt, &env.ledger().timestamp());
-        env.storage().instance().set(&DataKey::TotalPool, &0u128);
+        env.storage().instance().set(&DataKey::TotalPool, &0i128);
         env.storage().instance().set(&DataKey::TotalBettors, &0u64);
         env.storage().instance().set(&DataKey::WinningLivestreamId, &0u64);
+        env.storage().instance().set(&DataKey::FeeBps, &config.fee_bps);
+        env.storage().instance().set(&DataKey::FeeCollector, &config.fee_collector);
+        env.storage().instance().set(&DataKey::AccruedFees, &0i128);
+        env.storage().instance().set(&DataKey::BettingDeadline, &config.betting_deadline);
+        env.storage().instance().set(&DataKey::Oracles, &config.oracles);
+        env.storage().instance().set(&DataKey::Threshold, &config.threshold);
+        env.storage().instance().set(&DataKey::CreatorFeeBps, &config.creator_fee_bps);
+        env.storage().instance().set(&DataKey::Creator, &config.creator);
+        env.storage().instance().set(&DataKey::OpenTimeout, &config.open_timeout);
+        env.storage().instance().set(&DataKey::MaxUserStake, &config.max_user_stake);
+        env.storage().instance().set(&DataKey::Tags, &config.tags);
+        env.storage().instance().set(&DataKey::BetCooldown, &config.bet_cooldown);
+        env.storage().instance().set(&DataKey::RequireUniqueTitles, &config.require_unique_titles);
+        env.storage().instance().set(&DataKey::ClaimWindow, &config.claim_window);
+        if let Some(oracle_contract) = &config.oracle_contract {
+            env.storage().instance().set(&DataKey::OracleContract, oracle_contract);
+        }
+        env.storage().instance().set(&DataKey::Admin, config.admin.as_ref().unwrap_or(&oracle));
+        env.storage().instance().set(&DataKey::MinPool, &config.min_pool);
+        if let Some(payout_token) = &config.payout_token {
+            env.storage().instance().set(&ExtDataKey::PayoutToken, payout_token);
+            env.storage().instance().set(&ExtDataKey::PayoutRateBps, &config.payout_rate_bps);
+        }
+        env.storage().instance().set(&ExtDataKey::DrawFeeBps, &config.draw_fee_bps);
+        env.storage().instance().set(&ExtDataKey::MinBettors, &config.min_bettors);
+        env.storage().instance().set(&ExtDataKey::EmitBetEvents, &config.emit_bet_events);
+        env.storage().instance().set(&ExtDataKey::ReopenWindow, &config.reopen_window);
+        env.storage().instance().set(&ExtDataKey::MaxConcentrationBps, &config.max_concentration_bps);
+        assert_with_error!(&env, config.bet_tick >= 0, MarketError::InvalidAmount);
+        env.storage().instance().set(&ExtDataKey::BetTick, &config.bet_tick);
+        env.storage().instance().set(&ExtDataKey::MaxImbalanceBps, &config.max_imbalance_bps);
 
         // Initialize livestreams
         let mut ids_vec: Vec<u64> = Vec::new(&env);
@@ -77,7 +363,13 @@ impl PredictionMarket {
             let id = livestream_ids.get(i).unwrap();
             let title = livestream_titles.get(i).unwrap();
             
-            assert!(id != 0, "Invalid livestream ID");
+            assert_with_error!(&env, id != 0, MarketError::InvalidLivestream);
+            
+            if config.require_unique_titles {
+                for j in 0..i {
+                    assert_with_error!(&env, livestream_titles.get(j).unwrap() != title, MarketError::DuplicateTitle);
+                }
+            }
             
             let livestream = LivestreamData {
                 id,
@@ -91,7 +383,6 @@ impl PredictionMarket {
         }
         
         env.storage().instance().set(&DataKey::LivestreamIds, &ids_vec);
-        env.storage().instance().set(&DataKey::Bettors, &Vec::<Address>::new(&env));
 
         // Publish event
         env.events().publish(
@@ -109,17 +400,18 @@ impl PredictionMarket {
     ) {
         caller.require_auth();
         
-        let oracle: Address = env.storage().instance().get(&DataKey::Oracle).unwrap();
-        assert!(caller == oracle, "Not oracle");
+        Self::assert_admin(&env, &caller);
         
-        let state: State = env.storage().instance().get(&DataKey::State).unwrap();
-        assert!(state == State::Open, "Market not open");
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
         
-        assert!(livestream_id != 0, "Invalid livestream ID");
+        assert_with_error!(&env, livestream_id != 0, MarketError::InvalidLivestream);
         
         // Check if livestream already exists
         let exists = env.storage().persistent().has(&DataKey::Livestreams(livestream_id));
-        assert!(!exists, "Livestream already exists");
+        assert_with_error!(&env, !exists, MarketError::LivestreamExists);
+        
+        Self::assert_unique_title(&env, &title);
         
         let livestream = LivestreamData {
             id: livestream_id,
@@ -130,10 +422,12 @@ impl PredictionMarket {
         
         env.storage().persistent().set(&DataKey::Livestreams(livestream_id), &livestream);
         
-        let mut ids: Vec<u64> = env.storage().instance().get(&DataKey::LivestreamIds).unwrap();
+        let mut ids: Vec<u64> = Self::livestream_ids(&env);
         ids.push_back(livestream_id);
         env.storage().instance().set(&DataKey::LivestreamIds, &ids);
         
+        Self::bump_version(&env);
+        
         env.events().publish(
             (String::from_str(&env, "livestream_added"),),
             (livestream_id, title)
@@ -149,28 +443,66 @@ impl PredictionMarket {
     ) {
         caller.require_auth();
         
-        let oracle: Address = env.storage().instance().get(&DataKey::Oracle).unwrap();
-        assert!(caller == oracle, "Not oracle");
+        Self::assert_admin(&env, &caller);
         
-        let state: State = env.storage().instance().get(&DataKey::State).unwrap();
-        assert!(state == State::Open, "Market not open");
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
         
         let mut livestream: LivestreamData = env.storage()
             .persistent()
             .get(&DataKey::Livestreams(livestream_id))
-            .expect("Livestream not found");
+            .unwrap_or_else(|| panic_with_error!(&env, MarketError::LivestreamNotFound));
         
-        assert!(livestream.active, "Livestream not active");
+        assert_with_error!(&env, livestream.active, MarketError::LivestreamNotActive);
         
         livestream.title = new_title.clone();
         env.storage().persistent().set(&DataKey::Livestreams(livestream_id), &livestream);
         
+        Self::bump_version(&env);
+        
         env.events().publish(
             (String::from_str(&env, "livestream_updated"),),
             (livestream_id, new_title)
         );
     }
 
+    /// Update several livestream titles in one call
+    pub fn update_livestream_titles(
+        env: Env,
+        caller: Address,
+        livestream_ids: Vec<u64>,
+        titles: Vec<String>,
+    ) {
+        caller.require_auth();
+        
+        Self::assert_admin(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
+        
+        assert_with_error!(&env, livestream_ids.len() == titles.len(), MarketError::MismatchedArrays);
+        
+        for i in 0..livestream_ids.len() {
+            let livestream_id = livestream_ids.get(i).unwrap();
+            let mut livestream: LivestreamData = env.storage()
+                .persistent()
+                .get(&DataKey::Livestreams(livestream_id))
+                .unwrap_or_else(|| panic_with_error!(&env, MarketError::LivestreamNotFound));
+            
+            assert_with_error!(&env, livestream.active, MarketError::LivestreamNotActive);
+            
+            livestream.title = titles.get(i).unwrap();
+            env.storage().persistent().set(&DataKey::Livestreams(livestream_id), &livestream);
+        }
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "livestreams_updated"),),
+            (livestream_ids, titles)
+        );
+    }
+
     /// Add livestream with title (public function)
     pub fn add_livestream_with_title(
         env: Env,
@@ -180,15 +512,17 @@ impl PredictionMarket {
     ) {
         caller.require_auth();
         
-        let state: State = env.storage().instance().get(&DataKey::State).unwrap();
-        assert!(state == State::Open, "Market not open");
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
         
-        assert!(livestream_id > 0, "Invalid livestream ID");
-        assert!(title.len() > 0, "Title cannot be empty");
+        assert_with_error!(&env, livestream_id > 0, MarketError::InvalidLivestream);
+        assert_with_error!(&env, !title.is_empty(), MarketError::EmptyTitle);
         
         let exists = env.storage().persistent().has(&DataKey::Livestreams(livestream_id));
         
         if !exists {
+            Self::assert_unique_title(&env, &title);
+            
             let livestream = LivestreamData {
                 id: livestream_id,
                 title: title.clone(),
@@ -198,10 +532,12 @@ impl PredictionMarket {
             
             env.storage().persistent().set(&DataKey::Livestreams(livestream_id), &livestream);
             
-            let mut ids: Vec<u64> = env.storage().instance().get(&DataKey::LivestreamIds).unwrap();
+            let mut ids: Vec<u64> = Self::livestream_ids(&env);
             ids.push_back(livestream_id);
             env.storage().instance().set(&DataKey::LivestreamIds, &ids);
             
+            Self::bump_version(&env);
+            
             env.events().publish(
                 (String::from_str(&env, "livestream_added"),),
                 (livestream_id, title)
@@ -217,24 +553,23 @@ impl PredictionMarket {
     ) {
         caller.require_auth();
         
-        let oracle: Address = env.storage().instance().get(&DataKey::Oracle).unwrap();
-        assert!(caller == oracle, "Not oracle");
+        Self::assert_admin(&env, &caller);
         
-        let state: State = env.storage().instance().get(&DataKey::State).unwrap();
-        assert!(state == State::Open, "Market not open");
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
         
         let mut livestream: LivestreamData = env.storage()
             .persistent()
             .get(&DataKey::Livestreams(livestream_id))
-            .expect("Livestream not found");
+            .unwrap_or_else(|| panic_with_error!(&env, MarketError::LivestreamNotFound));
         
-        assert!(livestream.active, "Livestream not active");
+        assert_with_error!(&env, livestream.active, MarketError::LivestreamNotActive);
         
         livestream.active = false;
         env.storage().persistent().set(&DataKey::Livestreams(livestream_id), &livestream);
         
         // Remove from IDs array
-        let ids: Vec<u64> = env.storage().instance().get(&DataKey::LivestreamIds).unwrap();
+        let ids: Vec<u64> = Self::livestream_ids(&env);
         let mut new_ids: Vec<u64> = Vec::new(&env);
         for i in 0..ids.len() {
             let id = ids.get(i).unwrap();
@@ -244,224 +579,2251 @@ impl PredictionMarket {
         }
         env.storage().instance().set(&DataKey::LivestreamIds, &new_ids);
         
+        let mut removed: Vec<u64> = Self::removed_ids(&env);
+        removed.push_back(livestream_id);
+        env.storage().instance().set(&DataKey::RemovedIds, &removed);
+        
+        Self::bump_version(&env);
+        
         env.events().publish(
             (String::from_str(&env, "livestream_removed"),),
             livestream_id
         );
     }
 
-    /// Place a bet on a specific livestream
-    pub fn place_bet(
+    /// Bring a removed livestream back into the market
+    pub fn reactivate_livestream(
         env: Env,
-        user: Address,
+        caller: Address,
         livestream_id: u64,
-        amount: i128,
     ) {
-        user.require_auth();
+        caller.require_auth();
         
-        let state: State = env.storage().instance().get(&DataKey::State).unwrap();
-        assert!(state == State::Open, "Market not open");
-        assert!(amount > 0, "Amount must be positive");
-        assert!(livestream_id > 0, "Invalid livestream ID");
+        Self::assert_admin(&env, &caller);
         
-        // Auto-add livestream if it doesn't exist
-        let exists = env.storage().persistent().has(&DataKey::Livestreams(livestream_id));
-        if !exists {
-            let default_title = String::from_str(&env, "Project #");
-            
-            let livestream = LivestreamData {
-                id: livestream_id,
-                title: default_title,
-                active: true,
-                added_at: env.ledger().timestamp(),
-            };
-            
-            env.storage().persistent().set(&DataKey::Livestreams(livestream_id), &livestream);
-            
-            let mut ids: Vec<u64> = env.storage().instance().get(&DataKey::LivestreamIds).unwrap();
-            ids.push_back(livestream_id);
-            env.storage().instance().set(&DataKey::LivestreamIds, &ids);
-        }
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
         
-        // Transfer tokens from user to contract
-        let token_address = Address::from_string(&String::from_str(&env, "NATIVE"));
-        let token_client = token::Client::new(&env, &token_address);
-        token_client.transfer(&user, &env.current_contract_address(), &amount);
+        let mut livestream: LivestreamData = env.storage()
+            .persistent()
+            .get(&DataKey::Livestreams(livestream_id))
+            .unwrap_or_else(|| panic_with_error!(&env, MarketError::LivestreamNotFound));
         
-        // Track new bettor
-        let has_bet = env.storage().persistent().has(&DataKey::HasBet(user.clone()));
-        if !has_bet {
-            env.storage().persistent().set(&DataKey::HasBet(user.clone()), &true);
-            
-            let mut bettors: Vec<Address> = env.storage().instance().get(&DataKey::Bettors).unwrap();
-            bettors.push_back(user.clone());
-            env.storage().instance().set(&DataKey::Bettors, &bettors);
-            
-            let mut total_bettors: u64 = env.storage().instance().get(&DataKey::TotalBettors).unwrap();
-            total_bettors += 1;
-            env.storage().instance().set(&DataKey::TotalBettors, &total_bettors);
-        }
+        assert_with_error!(&env, !livestream.active, MarketError::LivestreamActive);
         
-        // Update bet amounts
-        let current_bet = env.storage()
-            .persistent()
-            .get(&DataKey::Bets(user.clone(), livestream_id))
-            .unwrap_or(0i128);
-        env.storage()
-            .persistent()
-            .set(&DataKey::Bets(user.clone(), livestream_id), &(current_bet + amount));
+        livestream.active = true;
+        env.storage().persistent().set(&DataKey::Livestreams(livestream_id), &livestream);
         
-        let current_total = env.storage()
-            .persistent()
-            .get(&DataKey::TotalBets(livestream_id))
-            .unwrap_or(0i128);
-        env.storage()
-            .persistent()
-            .set(&DataKey::TotalBets(livestream_id), &(current_total + amount));
+        let mut ids: Vec<u64> = Self::livestream_ids(&env);
+        ids.push_back(livestream_id);
+        env.storage().instance().set(&DataKey::LivestreamIds, &ids);
         
-        let total_pool: i128 = env.storage().instance().get(&DataKey::TotalPool).unwrap();
-        env.storage().instance().set(&DataKey::TotalPool, &(total_pool + amount));
+        let mut removed: Vec<u64> = Self::removed_ids(&env);
+        if let Some(index) = removed.first_index_of(livestream_id) {
+            removed.remove(index);
+        }
+        env.storage().instance().set(&DataKey::RemovedIds, &removed);
+        
+        Self::bump_version(&env);
         
         env.events().publish(
-            (String::from_str(&env, "bet_placed"),),
-            (user, livestream_id, amount, env.ledger().timestamp())
+            (String::from_str(&env, "livestream_reactivated"),),
+            livestream_id
         );
     }
 
-    /// Close the market
-    pub fn close_market(env: Env, caller: Address) {
+    /// Freeze or unfreeze betting on one livestream, e.g. a disqualified one
+    pub fn set_livestream_betting(env: Env, caller: Address, livestream_id: u64, enabled: bool) {
         caller.require_auth();
         
-        let oracle: Address = env.storage().instance().get(&DataKey::Oracle).unwrap();
-        assert!(caller == oracle, "Not oracle");
+        Self::assert_admin(&env, &caller);
         
-        let state: State = env.storage().instance().get(&DataKey::State).unwrap();
-        assert!(state == State::Open, "Market not open");
+        let exists = env.storage().persistent().has(&DataKey::Livestreams(livestream_id));
+        assert_with_error!(&env, exists, MarketError::LivestreamNotFound);
         
-        env.storage().instance().set(&DataKey::State, &State::Closed);
-        env.storage().instance().set(&DataKey::ClosedAt, &env.ledger().timestamp());
+        if enabled {
+            env.storage().persistent().remove(&ExtDataKey::LivestreamFrozen(livestream_id));
+        } else {
+            env.storage().persistent().set(&ExtDataKey::LivestreamFrozen(livestream_id), &true);
+        }
+        
+        Self::bump_version(&env);
         
         env.events().publish(
-            (String::from_str(&env, "market_closed"),),
-            env.ledger().timestamp()
+            (String::from_str(&env, "livestream_betting_set"),),
+            (livestream_id, enabled)
         );
     }
 
-    /// Resolve the market with a winning livestream
-    pub fn resolve_market(
-        env: Env,
-        caller: Address,
-        winning_livestream_id: u64,
-    ) {
+    /// Put a livestream in a group, e.g. a team; group 0 ungroups it
+    pub fn set_group(env: Env, caller: Address, livestream_id: u64, group_id: u64) {
         caller.require_auth();
         
-        let oracle: Address = env.storage().instance().get(&DataKey::Oracle).unwrap();
-        assert!(caller == oracle, "Not oracle");
+        Self::assert_admin(&env, &caller);
         
-        let state: State = env.storage().instance().get(&DataKey::State).unwrap();
-        assert!(state == State::Closed, "Market not closed");
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
         
-        let _livestream: LivestreamData = env.storage()
-            .persistent()
-            .get(&DataKey::Livestreams(winning_livestream_id))
-            .expect("Invalid winning livestream");
+        let exists = env.storage().persistent().has(&DataKey::Livestreams(livestream_id));
+        assert_with_error!(&env, exists, MarketError::LivestreamNotFound);
         
-        let total_bets: i128 = env.storage()
-            .persistent()
-            .get(&DataKey::TotalBets(winning_livestream_id))
-            .unwrap_or(0);
-        assert!(total_bets > 0, "No bets on this livestream");
+        if group_id == 0 {
+            env.storage().persistent().remove(&ExtDataKey::LivestreamGroup(livestream_id));
+        } else {
+            env.storage().persistent().set(&ExtDataKey::LivestreamGroup(livestream_id), &group_id);
+        }
         
-        env.storage().instance().set(&DataKey::WinningLivestreamId, &winning_livestream_id);
-        env.storage().instance().set(&DataKey::State, &State::Resolved);
-        env.storage().instance().set(&DataKey::ResolvedAt, &env.ledger().timestamp());
+        Self::bump_version(&env);
         
         env.events().publish(
-            (String::from_str(&env, "market_resolved"),),
-            (winning_livestream_id, env.ledger().timestamp())
+            (String::from_str(&env, "group_set"),),
+            (livestream_id, group_id)
         );
     }
 
-    /// Claim payout for winning bet
-    pub fn claim_payout(env: Env, user: Address) {
+    /// Place a bet on a specific livestream
+    pub fn place_bet(
+        env: Env,
+        user: Address,
+        livestream_id: u64,
+        amount: i128,
+    ) {
         user.require_auth();
         
-        let state: State = env.storage().instance().get(&DataKey::State).unwrap();
-        assert!(state == State::Resolved, "Market not resolved");
-        
-        let winning_id: u64 = env.storage().instance().get(&DataKey::WinningLivestreamId).unwrap();
+        Self::record_bet(&env, &user, livestream_id, amount);
+    }
+
+    /// Place a bet only if the implied payout ratio is still at least `min_payout_ratio_bps`
+    ///
+    /// The ratio is (total pool + amount) / (livestream pool + amount) in
+    /// basis points, i.e. 20000 means the bet would currently pay back 2x.
+    pub fn place_bet_protected(
+        env: Env,
+        user: Address,
+        livestream_id: u64,
+        amount: i128,
+        min_payout_ratio_bps: i128,
+    ) {
+        user.require_auth();
+        assert_with_error!(&env, amount > 0, MarketError::InvalidAmount);
         
-        let user_bet: i128 = env.storage()
+        let total_pool = Self::total_pool(&env);
+        let livestream_pool: i128 = env.storage()
             .persistent()
-            .get(&DataKey::Bets(user.clone(), winning_id))
+            .get(&DataKey::TotalBets(livestream_id))
             .unwrap_or(0);
-        assert!(user_bet > 0, "No winning bet");
+        let ratio_bps = ((total_pool + amount) * 10_000) / (livestream_pool + amount);
+        assert_with_error!(&env, ratio_bps >= min_payout_ratio_bps, MarketError::SlippageExceeded);
         
-        let winning_pool: i128 = env.storage()
-            .persistent()
-            .get(&DataKey::TotalBets(winning_id))
-            .unwrap();
-        assert!(winning_pool > 0, "No winning bets");
+        Self::record_bet(&env, &user, livestream_id, amount);
+    }
+
+    /// Place a bet crediting a referrer with the bet volume
+    ///
+    /// The referrer is only stored on the user's first bet; later bets keep
+    /// crediting whoever referred the user originally.
+    pub fn place_bet_with_referrer(
+        env: Env,
+        user: Address,
+        livestream_id: u64,
+        amount: i128,
+        referrer: Address,
+    ) {
+        user.require_auth();
+        assert_with_error!(&env, user != referrer, MarketError::SelfReferral);
         
-        let total_pool: i128 = env.storage().instance().get(&DataKey::TotalPool).unwrap();
+        let is_first_bet = !env.storage().persistent().has(&DataKey::HasBet(user.clone()));
         
-        // Calculate payout
-        let payout = (user_bet * total_pool) / winning_pool;
+        Self::record_bet(&env, &user, livestream_id, amount);
         
-        // Reset user's bet
-        env.storage().persistent().set(&DataKey::Bets(user.clone(), winning_id), &0i128);
+        if is_first_bet {
+            env.storage().persistent().set(&DataKey::Referrer(user.clone()), &referrer);
+        }
+        
+        let credited: Option<Address> = env.storage().persistent().get(&DataKey::Referrer(user.clone()));
+        if let Some(credited) = credited {
+            let volume: i128 = env.storage()
+                .persistent()
+                .get(&DataKey::ReferralVolume(credited.clone()))
+                .unwrap_or(0);
+            env.storage()
+                .persistent()
+                .set(&DataKey::ReferralVolume(credited.clone()), &(volume + amount));
+            
+            env.events().publish(
+                (String::from_str(&env, "referral_recorded"),),
+                (credited, user, amount)
+            );
+        }
+    }
+
+    /// Add tokens to the prize pool without backing any livestream
+    ///
+    /// The bonus is shared by the winners pro-rata to their winning stake.
+    pub fn add_bonus_pool(env: Env, sponsor: Address, amount: i128) {
+        sponsor.require_auth();
+        
+        // Cancelled and drawn markets only refund stakes, so a bonus there would be stuck
+        let state = Self::state(&env);
+        assert_with_error!(
+            &env,
+            state == State::Open || state == State::Closed,
+            ExtMarketError::MarketSettled
+        );
+        assert_with_error!(&env, amount > 0, MarketError::InvalidAmount);
         
-        // Transfer payout
-        let token_address = Address::from_string(&String::from_str(&env, "NATIVE"));
+        let token_address: Address = env.storage().instance().get(&DataKey::Token).unwrap();
         let token_client = token::Client::new(&env, &token_address);
-        token_client.transfer(&env.current_contract_address(), &user, &payout);
+        token_client.transfer(&sponsor, env.current_contract_address(), &amount);
+        
+        let total_pool = Self::total_pool(&env);
+        env.storage().instance().set(&DataKey::TotalPool, &(total_pool + amount));
+        
+        Self::bump_version(&env);
         
         env.events().publish(
-            (String::from_str(&env, "payout_claimed"),),
-            (user, payout, env.ledger().timestamp())
+            (String::from_str(&env, "bonus_added"),),
+            (sponsor, amount)
         );
     }
 
-    /// Get market information
-    pub fn get_market_info(env: Env) -> (Vec<u64>, String, State, u64, i128, u64) {
-        let livestream_ids: Vec<u64> = env.storage().instance().get(&DataKey::LivestreamIds).unwrap_or(Vec::new(&env));
-        let question: String = env.storage().instance().get(&DataKey::Question).unwrap_or(String::from_str(&env, ""));
-        let state: State = env.storage().instance().get(&DataKey::State).unwrap_or(State::Open);
-        let winning_id: u64 = env.storage().instance().get(&DataKey::WinningLivestreamId).unwrap_or(0);
-        let total_pool: i128 = env.storage().instance().get(&DataKey::TotalPool).unwrap_or(0);
-        let total_bettors: u64 = env.storage().instance().get(&DataKey::TotalBettors).unwrap_or(0);
+    /// Refund all of a user's bets and remove them from the market
+    pub fn exit_market(env: Env, user: Address) {
+        user.require_auth();
+        
+        Self::assert_trading(&env);
+        
+        // A pending fork still has to find this user's forked bets
+        let forking = env.storage().instance().has(&ExtDataKey::ForkProgress);
+        assert_with_error!(&env, !forking, ExtMarketError::ForkPending);
+        
+        let has_bet = env.storage().persistent().has(&DataKey::HasBet(user.clone()));
+        assert_with_error!(&env, has_bet, MarketError::NotABettor);
+        
+        // Removed livestreams can still hold stakes, so refund those too
+        let mut ids: Vec<u64> = Self::livestream_ids(&env);
+        let removed: Vec<u64> = Self::removed_ids(&env);
+        ids.append(&removed);
+        
+        let mut refund: i128 = 0;
+        for id in ids.iter() {
+            let user_bet: i128 = env.storage()
+                .persistent()
+                .get(&DataKey::Bets(user.clone(), id))
+                .unwrap_or(0);
+            if user_bet == 0 {
+                continue;
+            }
+            
+            let total_bets: i128 = env.storage().persistent().get(&DataKey::TotalBets(id)).unwrap_or(0);
+            env.storage().persistent().set(&DataKey::TotalBets(id), &(total_bets - user_bet));
+            env.storage().persistent().set(&DataKey::Bets(user.clone(), id), &0i128);
+            Self::adjust_livestream_bettors(&env, id, -1);
+            refund += user_bet;
+        }
+        
+        let total_pool = Self::total_pool(&env);
+        env.storage().instance().set(&DataKey::TotalPool, &(total_pool - refund));
+        
+        Self::untrack_bettor(&env, &user);
+        
+        if refund > 0 {
+            Self::send_tokens(&env, &user, refund);
+        }
+        
+        Self::bump_version(&env);
         
-        (livestream_ids, question, state, winning_id, total_pool, total_bettors)
+        env.events().publish(
+            (String::from_str(&env, "user_exited"),),
+            (user, refund)
+        );
     }
 
-    /// Get livestream betting data
-    pub fn get_livestream_bets(env: Env, livestream_id: u64) -> (i128, u64, bool) {
-        let livestream: LivestreamData = env.storage()
+    /// Move part of a stake on a livestream to another user; no tokens move
+    pub fn transfer_position(env: Env, from: Address, to: Address, livestream_id: u64, amount: i128) {
+        from.require_auth();
+        
+        Self::assert_trading(&env);
+        
+        let frozen = env.storage().persistent().has(&ExtDataKey::LivestreamFrozen(livestream_id));
+        assert_with_error!(&env, !frozen, ExtMarketError::LivestreamFrozen);
+        
+        // The recipient's stake cap applies as if they had placed the bet themselves
+        let max_user_stake: i128 = env.storage().instance().get(&DataKey::MaxUserStake).unwrap_or(0);
+        if max_user_stake > 0 {
+            let staked = Self::get_user_total_stake(env.clone(), to.clone());
+            assert_with_error!(&env, staked + amount <= max_user_stake, MarketError::UserStakeCapExceeded);
+        }
+        
+        let from_bet: i128 = env.storage()
             .persistent()
-            .get(&DataKey::Livestreams(livestream_id))
-            .expect("Livestream not found");
+            .get(&DataKey::Bets(from.clone(), livestream_id))
+            .unwrap_or(0);
+        assert_with_error!(&env, amount > 0 && amount <= from_bet, MarketError::InvalidAmount);
+        env.storage().persistent().set(&DataKey::Bets(from.clone(), livestream_id), &(from_bet - amount));
+        if from_bet == amount {
+            Self::adjust_livestream_bettors(&env, livestream_id, -1);
+        }
         
-        let amount: i128 = env.storage()
+        let to_bet: i128 = env.storage()
             .persistent()
-            .get(&DataKey::TotalBets(livestream_id))
+            .get(&DataKey::Bets(to.clone(), livestream_id))
             .unwrap_or(0);
+        env.storage().persistent().set(&DataKey::Bets(to.clone(), livestream_id), &(to_bet + amount));
+        if to_bet == 0 {
+            Self::adjust_livestream_bettors(&env, livestream_id, 1);
+        }
         
-        let total_pool: i128 = env.storage().instance().get(&DataKey::TotalPool).unwrap();
-        let percentage = if total_pool > 0 {
-            ((amount * 100) / total_pool) as u64
-        } else {
-            0
-        };
+        Self::track_bettor(&env, &to);
         
-        (amount, percentage, livestream.active)
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "position_transferred"),),
+            (from, to, livestream_id, amount)
+        );
     }
 
-    /// Get user's bet on a specific livestream
-    pub fn get_user_bet(env: Env, user: Address, livestream_id: u64) -> i128 {
-        env.storage()
-            .persistent()
-            .get(&DataKey::Bets(user, livestream_id))
-            .unwrap_or(0)
+    /// Get bettors (paginated)
+    ///
+    /// New bettors are appended, but `exit_market` moves the last bettor into
+    /// the leaver's slot, so the order is only the join order until someone exits.
+    pub fn get_bettors(env: Env, offset: u32, limit: u32) -> Vec<Address> {
+        let mut result: Vec<Address> = Vec::new(&env);
+        
+        let total_bettors: u64 = Self::total_bettors(&env);
+        let end = offset.saturating_add(limit).min(total_bettors as u32);
+        let mut page_no = u32::MAX;
+        let mut page: Vec<Address> = Vec::new(&env);
+        for i in offset..end {
+            if i / BETTOR_PAGE_SIZE != page_no {
+                page_no = i / BETTOR_PAGE_SIZE;
+                page = env.storage().persistent().get(&DataKey::BettorPage(page_no)).unwrap();
+            }
+            result.push_back(page.get(i % BETTOR_PAGE_SIZE).unwrap());
+        }
+        
+        result
+    }
+
+    /// Get (bettor, amount) for each bettor in a page of `get_bettors` who backed a livestream
+    ///
+    /// `offset` and `limit` page through all bettors, so a page can hold
+    /// fewer pairs than `limit` when some bettors are on other livestreams.
+    pub fn get_livestream_bettors(env: Env, livestream_id: u64, offset: u32, limit: u32) -> Vec<(Address, i128)> {
+        let bettors = Self::get_bettors(env.clone(), offset, limit);
+        
+        let mut result: Vec<(Address, i128)> = Vec::new(&env);
+        for bettor in bettors.iter() {
+            let amount: i128 = env.storage()
+                .persistent()
+                .get(&DataKey::Bets(bettor.clone(), livestream_id))
+                .unwrap_or(0);
+            if amount > 0 {
+                result.push_back((bettor, amount));
+            }
+        }
+        
+        result
+    }
+
+    /// Exempt a user from platform and creator fees on their claims, or revoke it
+    pub fn set_fee_exempt(env: Env, caller: Address, who: Address, exempt: bool) {
+        caller.require_auth();
+        
+        Self::assert_admin(&env, &caller);
+        
+        if exempt {
+            env.storage().persistent().set(&ExtDataKey::FeeExempt(who.clone()), &true);
+        } else {
+            env.storage().persistent().remove(&ExtDataKey::FeeExempt(who.clone()));
+        }
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "fee_exemption_set"),),
+            (who, exempt)
+        );
+    }
+
+    /// Temporarily stop accepting bets
+    pub fn pause(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        Self::assert_admin(&env, &caller);
+        
+        let paused: bool = env.storage().instance().get(&DataKey::Paused).unwrap_or(false);
+        assert_with_error!(&env, !paused, MarketError::Paused);
+        
+        env.storage().instance().set(&DataKey::Paused, &true);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "market_paused"),),
+            env.ledger().timestamp()
+        );
+    }
+
+    /// Resume accepting bets
+    pub fn unpause(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        Self::assert_admin(&env, &caller);
+        
+        let paused: bool = env.storage().instance().get(&DataKey::Paused).unwrap_or(false);
+        assert_with_error!(&env, paused, MarketError::NotPaused);
+        
+        env.storage().instance().set(&DataKey::Paused, &false);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "market_unpaused"),),
+            env.ledger().timestamp()
+        );
+    }
+
+    /// Push the betting deadline back; it can never be moved earlier
+    pub fn extend_deadline(env: Env, caller: Address, new_deadline: u64) {
+        caller.require_auth();
+        
+        Self::assert_oracle(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
+        
+        let current_deadline: u64 = env.storage().instance().get(&DataKey::BettingDeadline).unwrap_or(0);
+        assert_with_error!(&env, current_deadline > 0, MarketError::NoDeadline);
+        assert_with_error!(&env, new_deadline > current_deadline, MarketError::DeadlineNotExtended);
+        
+        env.storage().instance().set(&DataKey::BettingDeadline, &new_deadline);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "deadline_extended"),),
+            (current_deadline, new_deadline)
+        );
+    }
+
+    /// Close the market
+    pub fn close_market(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        Self::assert_oracle(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
+        
+        Self::close_and_notify(&env);
+    }
+
+    /// Undo a premature close, within the market's reopen window
+    pub fn reopen_market(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        Self::assert_oracle(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Closed, MarketError::NotClosed);
+        
+        let reopen_window: u64 = env.storage().instance().get(&ExtDataKey::ReopenWindow).unwrap_or(0);
+        let closed_at: u64 = env.storage().instance().get(&DataKey::ClosedAt).unwrap();
+        assert_with_error!(
+            &env,
+            reopen_window > 0 && env.ledger().timestamp() <= closed_at + reopen_window,
+            ExtMarketError::ReopenWindowClosed
+        );
+        
+        env.storage().instance().set(&DataKey::State, &State::Open);
+        env.storage().instance().remove(&DataKey::ClosedAt);
+        
+        // Take back the stats reported at close; they are reported again on the next one
+        let factory: Address = env.storage().instance().get(&DataKey::Factory).unwrap();
+        let total_pool = Self::total_pool(&env);
+        let total_bettors: u64 = Self::total_bettors(&env);
+        let _ = FactoryClient::new(&env, &factory)
+            .try_notify_market_reopened(&env.current_contract_address(), &total_pool, &total_bettors);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "market_reopened"),),
+            env.ledger().timestamp()
+        );
+    }
+
+    /// Close and resolve in one call, for single-oracle markets
+    pub fn close_and_resolve(env: Env, caller: Address, winning_livestream_id: u64) {
+        caller.require_auth();
+        
+        Self::assert_oracle(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
+        
+        Self::close_and_notify(&env);
+        Self::resolve_by_oracle(&env, &caller, winning_livestream_id);
+    }
+
+    /// Close a market the oracle left open past its open timeout
+    ///
+    /// Only the factory can call this; it records the state change on its
+    /// own side, so no callback is made.
+    pub fn force_close(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        Self::assert_factory(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
+        
+        let open_timeout: u64 = env.storage().instance().get(&DataKey::OpenTimeout).unwrap_or(0);
+        let created_at: u64 = env.storage().instance().get(&DataKey::CreatedAt).unwrap();
+        assert_with_error!(
+            &env,
+            open_timeout > 0 && env.ledger().timestamp() >= created_at + open_timeout,
+            MarketError::OpenTimeoutNotReached
+        );
+        
+        Self::close(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "market_force_closed"),),
+            env.ledger().timestamp()
+        );
+    }
+
+    /// Move livestreams and every stake on them to `destination`, for a factory fork
+    ///
+    /// The first page (`offset` 0) detaches the livestreams and sends their
+    /// whole pool to `destination`; they stay behind as inactive, frozen
+    /// entries so bets can't recreate them. Each page then checks up to `limit`
+    /// bettors and returns the bets it moved as (user, livestream_id, amount),
+    /// along with how many bettors are left to check. Pages must come in order,
+    /// each starting where the last one stopped. Bettors left without any
+    /// stake stop counting as bettors here.
+    pub fn fork_out(
+        env: Env,
+        caller: Address,
+        livestream_ids: Vec<u64>,
+        destination: Address,
+        offset: u32,
+        limit: u32,
+    ) -> (Vec<(Address, u64, i128)>, u32) {
+        caller.require_auth();
+        
+        Self::assert_factory(&env, &caller);
+        
+        let progress: Option<(Vec<u64>, Address, u32, u32)> = env.storage().instance().get(&ExtDataKey::ForkProgress);
+        let mut remaining = match progress {
+            Some((ids, to, checked, remaining)) => {
+                let in_order = ids == livestream_ids && to == destination && offset == checked;
+                assert_with_error!(&env, in_order, ExtMarketError::ForkOutOfOrder);
+                remaining
+            }
+            None => {
+                assert_with_error!(&env, offset == 0, ExtMarketError::ForkOutOfOrder);
+                Self::start_fork(&env, &livestream_ids, &destination);
+                Self::total_bettors(&env) as u32
+            }
+        };
+        
+        let mut stake_ids = Self::livestream_ids(&env);
+        stake_ids.append(&Self::removed_ids(&env));
+        
+        // Walk down from the end: `untrack_bettor` refills a slot from the last
+        // bettor, who has either been checked already or joined after the fork
+        let mut moved: Vec<(Address, u64, i128)> = Vec::new(&env);
+        let end = remaining.saturating_sub(limit);
+        let checked = offset + (remaining - end);
+        while remaining > end {
+            remaining -= 1;
+            let bettor = Self::get_bettors(env.clone(), remaining, 1).get(0).unwrap();
+            for id in livestream_ids.iter() {
+                let amount: i128 = env.storage()
+                    .persistent()
+                    .get(&DataKey::Bets(bettor.clone(), id))
+                    .unwrap_or(0);
+                if amount > 0 {
+                    env.storage().persistent().remove(&DataKey::Bets(bettor.clone(), id));
+                    moved.push_back((bettor.clone(), id, amount));
+                }
+            }
+            
+            // Bettors whose whole stake moved only count in the destination now
+            let has_stake = stake_ids.iter().any(|id| {
+                env.storage().persistent().get::<_, i128>(&DataKey::Bets(bettor.clone(), id)).unwrap_or(0) > 0
+            });
+            if !has_stake {
+                Self::untrack_bettor(&env, &bettor);
+            }
+        }
+        
+        if remaining == 0 {
+            env.storage().instance().remove(&ExtDataKey::ForkProgress);
+        } else {
+            env.storage().instance().set(&ExtDataKey::ForkProgress, &(livestream_ids, destination, checked, remaining));
+        }
+        
+        Self::bump_version(&env);
+        
+        (moved, remaining)
+    }
+
+    /// Record bets forked in from another market, whose tokens it has already sent
+    pub fn fork_in(env: Env, caller: Address, bets: Vec<(Address, u64, i128)>) {
+        caller.require_auth();
+        
+        Self::assert_factory(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
+        
+        let mut moved_total: i128 = 0;
+        for (user, id, amount) in bets.iter() {
+            let exists = env.storage().persistent().has(&DataKey::Livestreams(id));
+            assert_with_error!(&env, exists, MarketError::LivestreamNotFound);
+            
+            let current_bet: i128 = env.storage().persistent().get(&DataKey::Bets(user.clone(), id)).unwrap_or(0);
+            env.storage().persistent().set(&DataKey::Bets(user.clone(), id), &(current_bet + amount));
+            if current_bet == 0 {
+                Self::adjust_livestream_bettors(&env, id, 1);
+            }
+            
+            let total_bets: i128 = env.storage().persistent().get(&DataKey::TotalBets(id)).unwrap_or(0);
+            env.storage().persistent().set(&DataKey::TotalBets(id), &(total_bets + amount));
+            
+            Self::track_bettor(&env, &user);
+            moved_total += amount;
+        }
+        
+        let total_pool = Self::total_pool(&env);
+        env.storage().instance().set(&DataKey::TotalPool, &(total_pool + moved_total));
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "bets_forked_in"),),
+            (bets.len(), moved_total)
+        );
+    }
+
+    /// Resolve the market with a winning livestream
+    pub fn resolve_market(
+        env: Env,
+        caller: Address,
+        winning_livestream_id: u64,
+    ) {
+        caller.require_auth();
+        Self::resolve_by_oracle(&env, &caller, winning_livestream_id);
+    }
+
+    /// Resolve the market and anchor a proof of the outcome
+    ///
+    /// The proof isn't verified on-chain; it's stored as-is so the result
+    /// can be checked off-chain if it is disputed.
+    pub fn resolve_market_with_proof(
+        env: Env,
+        caller: Address,
+        winning_livestream_id: u64,
+        proof: Bytes,
+    ) {
+        caller.require_auth();
+        Self::resolve_by_oracle(&env, &caller, winning_livestream_id);
+        env.storage().instance().set(&DataKey::ResolutionProof, &proof);
+    }
+
+    /// Resolve by splitting the pool across livestreams in proportion to weights
+    ///
+    /// A bettor's share is their stake on each livestream times its weight,
+    /// over the weighted total of all stakes. A single winner is the special
+    /// case of weight 1 for the winner and 0 elsewhere. `winning_id` reports
+    /// the highest-weighted livestream.
+    pub fn resolve_weighted(env: Env, caller: Address, livestream_ids: Vec<u64>, weights: Vec<u64>) {
+        caller.require_auth();
+        
+        Self::assert_oracle(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Closed, MarketError::NotClosed);
+        
+        let threshold: u32 = env.storage().instance().get(&DataKey::Threshold).unwrap_or(0);
+        assert_with_error!(&env, threshold == 0, MarketError::MultiOracleMarket);
+        
+        assert_with_error!(
+            &env,
+            !livestream_ids.is_empty() && livestream_ids.len() == weights.len(),
+            MarketError::MismatchedArrays
+        );
+        
+        // Removed livestreams have no close snapshot, so they can't take a weight
+        let active_ids: Vec<u64> = Self::livestream_ids(&env);
+        let mut weighted_total: i128 = 0;
+        let mut top_id: u64 = 0;
+        let mut top_weight: u64 = 0;
+        for i in 0..livestream_ids.len() {
+            let id = livestream_ids.get(i).unwrap();
+            let weight = weights.get(i).unwrap();
+            
+            assert_with_error!(&env, active_ids.contains(id), ExtMarketError::WinnerNotInMarket);
+            assert_with_error!(
+                &env,
+                !env.storage().persistent().has(&DataKey::ResolutionWeight(id)),
+                MarketError::InvalidWeights
+            );
+            
+            let snapshot: i128 = env.storage().persistent().get(&DataKey::ClosedSnapshot(id)).unwrap_or(0);
+            weighted_total += snapshot * weight as i128;
+            env.storage().persistent().set(&DataKey::ResolutionWeight(id), &weight);
+            
+            if weight > top_weight {
+                top_weight = weight;
+                top_id = id;
+            }
+        }
+        assert_with_error!(&env, weighted_total > 0, MarketError::InvalidWeights);
+        
+        env.storage().instance().set(&DataKey::WeightedIds, &livestream_ids);
+        env.storage().instance().set(&DataKey::WinningLivestreamId, &top_id);
+        Self::mark_resolved(&env, &caller);
+        
+        env.events().publish(
+            (String::from_str(&env, "market_resolved_weighted"),),
+            (livestream_ids, weights, env.ledger().timestamp())
+        );
+    }
+
+    /// Resolve a closed market with the winner reported by its oracle contract
+    ///
+    /// Anyone can call this; the outcome comes entirely from the data feed.
+    pub fn auto_resolve(env: Env) {
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Closed, MarketError::NotClosed);
+        
+        let oracle_contract: Address = env.storage().instance().get(&DataKey::OracleContract)
+            .unwrap_or_else(|| panic_with_error!(&env, MarketError::NoOracleContract));
+        let winner = DataFeedClient::new(&env, &oracle_contract).get_winner(&env.current_contract_address());
+        
+        Self::finalize_resolution(&env, &oracle_contract, winner);
+    }
+
+    /// Get the payout per betting token on the winner, scaled by 1e9
+    ///
+    /// Fixed at resolution; 0 before then and for weighted resolutions.
+    pub fn get_payout_multiplier(env: Env) -> i128 {
+        env.storage().instance().get(&ExtDataKey::PayoutMultiplier).unwrap_or(0)
+    }
+
+    /// Get (claimed_count, total_winners); both are 0 before resolution
+    ///
+    /// On weighted markets total_winners can overcount users who backed several
+    /// paying livestreams until they claim.
+    pub fn get_claim_stats(env: Env) -> (u64, u64) {
+        let claimed_count: u64 = env.storage().instance().get(&ExtDataKey::ClaimedCount).unwrap_or(0);
+        let total_winners: u64 = env.storage().instance().get(&ExtDataKey::TotalWinners).unwrap_or(0);
+        (claimed_count, total_winners)
+    }
+
+    /// Get how the market was resolved: (winning_id, resolved_at, resolver)
+    ///
+    /// For multi-oracle markets the resolver is the oracle whose vote reached
+    /// the threshold; for `auto_resolve` it is the oracle contract.
+    pub fn get_resolution(env: Env) -> (u64, u64, Address) {
+        let resolver: Address = env.storage().instance().get(&ExtDataKey::Resolver)
+            .unwrap_or_else(|| panic_with_error!(&env, MarketError::NotResolved));
+        let winning_id: u64 = env.storage().instance().get(&DataKey::WinningLivestreamId).unwrap();
+        let resolved_at: u64 = env.storage().instance().get(&DataKey::ResolvedAt).unwrap();
+        (winning_id, resolved_at, resolver)
+    }
+
+    /// Get the proof stored at resolution (empty if none was given)
+    pub fn get_resolution_proof(env: Env) -> Bytes {
+        env.storage().instance().get(&DataKey::ResolutionProof).unwrap_or(Bytes::new(&env))
+    }
+
+    /// Vote for a winning livestream as one of the market's oracles
+    ///
+    /// The market resolves as soon as `threshold` oracles agree on the same
+    /// livestream. Oracles may change their vote until then.
+    pub fn submit_resolution(env: Env, oracle: Address, winning_livestream_id: u64) {
+        oracle.require_auth();
+        
+        let oracles: Vec<Address> = env.storage().instance().get(&DataKey::Oracles).unwrap_or(Vec::new(&env));
+        assert_with_error!(&env, oracles.contains(&oracle), MarketError::NotOracle);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Closed, MarketError::NotClosed);
+        
+        Self::validate_winner(&env, winning_livestream_id);
+        
+        env.storage().instance().set(&DataKey::ResolutionVote(oracle.clone()), &winning_livestream_id);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "resolution_submitted"),),
+            (oracle.clone(), winning_livestream_id)
+        );
+        
+        let mut votes: u32 = 0;
+        for i in 0..oracles.len() {
+            let vote: Option<u64> = env.storage()
+                .instance()
+                .get(&DataKey::ResolutionVote(oracles.get(i).unwrap()));
+            if vote == Some(winning_livestream_id) {
+                votes += 1;
+            }
+        }
+        
+        let threshold: u32 = env.storage().instance().get(&DataKey::Threshold).unwrap();
+        if votes >= threshold {
+            Self::finalize_resolution(&env, &oracle, winning_livestream_id);
+        }
+    }
+
+    /// Claim payout for winning bet
+    pub fn claim_payout(env: Env, user: Address) {
+        Self::claim_payout_to(env, user.clone(), user);
+    }
+
+    /// Claim payout for `user`'s winning bet, sending the tokens to `recipient`
+    pub fn claim_payout_to(env: Env, user: Address, recipient: Address) {
+        user.require_auth();
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Resolved, MarketError::NotResolved);
+        
+        let claim_deadline: u64 = env.storage().instance().get(&DataKey::ClaimDeadline).unwrap_or(0);
+        assert_with_error!(
+            &env,
+            claim_deadline == 0 || env.ledger().timestamp() <= claim_deadline,
+            MarketError::ClaimDeadlinePassed
+        );
+        
+        Self::pay_winnings(&env, &user, &recipient);
+    }
+
+    /// Opt in to being paid by `process_auto_claims` instead of claiming
+    pub fn enable_auto_claim(env: Env, user: Address) {
+        user.require_auth();
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Open, MarketError::NotOpen);
+        
+        env.storage().persistent().set(&ExtDataKey::AutoClaim(user.clone()), &true);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "auto_claim_enabled"),),
+            user
+        );
+    }
+
+    /// Pay opted-in winners among bettors `offset..offset + limit`
+    ///
+    /// Anyone can call this, page by page, after resolution. Returns how many
+    /// users were paid.
+    pub fn process_auto_claims(env: Env, offset: u32, limit: u32) -> u32 {
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Resolved, MarketError::NotResolved);
+        
+        let claim_deadline: u64 = env.storage().instance().get(&DataKey::ClaimDeadline).unwrap_or(0);
+        assert_with_error!(
+            &env,
+            claim_deadline == 0 || env.ledger().timestamp() <= claim_deadline,
+            MarketError::ClaimDeadlinePassed
+        );
+        
+        let mut paid: u32 = 0;
+        for bettor in Self::get_bettors(env.clone(), offset, limit).iter() {
+            let opted_in = env.storage().persistent().has(&ExtDataKey::AutoClaim(bettor.clone()));
+            if opted_in && Self::has_winnings(&env, &bettor) {
+                Self::pay_winnings(&env, &bettor, &bettor);
+                paid += 1;
+            }
+        }
+        
+        paid
+    }
+
+    /// Cancel an unresolved market so every bettor can reclaim their stake
+    pub fn cancel_market(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        Self::assert_oracle(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(
+            &env,
+            state == State::Open || state == State::Closed,
+            MarketError::CannotCancel
+        );
+        
+        // Go through Closed first so the factory stops listing the market as open
+        if state == State::Open {
+            Self::close_and_notify(&env);
+        }
+        env.storage().instance().set(&DataKey::State, &State::Cancelled);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "market_cancelled"),),
+            env.ledger().timestamp()
+        );
+    }
+
+    /// End a closed market without a winner, e.g. a tie, so every bettor is refunded
+    ///
+    /// Unlike `cancel_market`, which voids a market opened in error, a draw is a
+    /// legitimate outcome and refunds pay the market's draw processing fee.
+    pub fn resolve_draw(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        Self::assert_oracle(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Closed, MarketError::NotClosed);
+        
+        env.storage().instance().set(&DataKey::State, &State::Draw);
+        env.storage().instance().set(&DataKey::ResolvedAt, &env.ledger().timestamp());
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "market_draw"),),
+            env.ledger().timestamp()
+        );
+    }
+
+    /// Return all of a user's bets from a cancelled or drawn market
+    ///
+    /// After a draw the processing fee is kept back for the fee collector.
+    /// In a resolved market only stakes on livestreams removed before
+    /// resolution are refunded, within the same claim window as payouts.
+    pub fn claim_refund(env: Env, user: Address) {
+        user.require_auth();
+        
+        let state = Self::state(&env);
+        assert_with_error!(
+            &env,
+            state == State::Cancelled || state == State::Draw || state == State::Resolved,
+            MarketError::NotCancelled
+        );
+        
+        let removed: Vec<u64> = Self::removed_ids(&env);
+        let mut ids: Vec<u64> = Vec::new(&env);
+        if state == State::Resolved {
+            // `sweep_unclaimed` takes leftover refunds along with leftover winnings
+            let claim_deadline: u64 = env.storage().instance().get(&DataKey::ClaimDeadline).unwrap_or(0);
+            assert_with_error!(
+                &env,
+                claim_deadline == 0 || env.ledger().timestamp() <= claim_deadline,
+                MarketError::ClaimDeadlinePassed
+            );
+            for id in removed.iter() {
+                if env.storage().persistent().has(&ExtDataKey::RefundableLivestream(id)) {
+                    ids.push_back(id);
+                }
+            }
+        } else {
+            ids = Self::livestream_ids(&env);
+            ids.append(&removed);
+        }
+        
+        let mut refund: i128 = 0;
+        for id in ids.iter() {
+            let user_bet: i128 = env.storage()
+                .persistent()
+                .get(&DataKey::Bets(user.clone(), id))
+                .unwrap_or(0);
+            if user_bet > 0 {
+                env.storage().persistent().set(&DataKey::Bets(user.clone(), id), &0i128);
+                refund += user_bet;
+            }
+        }
+        assert_with_error!(&env, refund > 0, MarketError::NotABettor);
+        
+        if state == State::Draw {
+            let draw_fee_bps: u32 = env.storage().instance().get(&ExtDataKey::DrawFeeBps).unwrap_or(0);
+            let fee = (refund * draw_fee_bps as i128) / 10_000;
+            if fee > 0 {
+                let accrued: i128 = env.storage().instance().get(&DataKey::AccruedFees).unwrap_or(0);
+                env.storage().instance().set(&DataKey::AccruedFees, &(accrued + fee));
+                refund -= fee;
+            }
+        }
+        
+        Self::send_tokens(&env, &user, refund);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "refund_claimed"),),
+            (user, refund)
+        );
+    }
+
+    /// Arm `emergency_withdraw`, which unlocks after the timelock
+    pub fn enable_emergency(env: Env, caller: Address) {
+        caller.require_auth();
+        Self::assert_factory_owner(&env, &caller);
+        
+        env.storage().instance().set(&DataKey::EmergencyEnabled, &env.ledger().timestamp());
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "emergency_enabled"),),
+            env.ledger().timestamp() + EMERGENCY_TIMELOCK
+        );
+    }
+
+    /// Move the market's whole balance out as a last-resort recovery
+    pub fn emergency_withdraw(env: Env, caller: Address, to: Address) {
+        caller.require_auth();
+        Self::assert_factory_owner(&env, &caller);
+        
+        let enabled_at: u64 = env.storage().instance().get(&DataKey::EmergencyEnabled)
+            .unwrap_or_else(|| panic_with_error!(&env, MarketError::EmergencyNotEnabled));
+        assert_with_error!(
+            &env,
+            env.ledger().timestamp() >= enabled_at + EMERGENCY_TIMELOCK,
+            MarketError::EmergencyTimelocked
+        );
+        
+        let balance = Self::token_balance(&env);
+        if balance > 0 {
+            Self::send_tokens(&env, &to, balance);
+        }
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "emergency_withdrawn"),),
+            (to, balance)
+        );
+    }
+
+    /// Send winnings and refunds nobody claimed before the claim deadline to the fee collector
+    pub fn sweep_unclaimed(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        Self::assert_oracle(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Resolved, MarketError::NotResolved);
+        
+        let claim_deadline: u64 = env.storage().instance().get(&DataKey::ClaimDeadline).unwrap_or(0);
+        assert_with_error!(
+            &env,
+            claim_deadline > 0 && env.ledger().timestamp() > claim_deadline,
+            MarketError::ClaimWindowOpen
+        );
+        
+        // Everything except tokens already owed to the collector is unclaimed winnings
+        let unclaimed = Self::token_balance(&env) - Self::owed_to_collector(&env);
+        assert_with_error!(&env, unclaimed > 0, MarketError::NothingToSweep);
+        
+        let fee_collector: Address = env.storage().instance().get(&DataKey::FeeCollector).unwrap();
+        Self::send_tokens(&env, &fee_collector, unclaimed);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "unclaimed_swept"),),
+            (fee_collector, unclaimed)
+        );
+    }
+
+    /// Delete per-bettor and per-livestream bet entries of a settled market to reclaim rent
+    ///
+    /// Works page by page over bettors `offset..offset + limit`, in order: each
+    /// page must start where the last one stopped, and the page that reaches
+    /// the last bettor also clears the per-livestream totals. Every
+    /// winning and refundable stake must have been claimed, unless the claim
+    /// window has passed and the leftovers were swept. Livestream data and the
+    /// market summary stay readable. Returns how many bettors were cleaned.
+    pub fn cleanup(env: Env, caller: Address, offset: u32, limit: u32) -> u32 {
+        caller.require_auth();
+        
+        Self::assert_oracle(&env, &caller);
+        
+        let state = Self::state(&env);
+        assert_with_error!(&env, state == State::Resolved, MarketError::NotResolved);
+        
+        // Totals are only cleared once every bettor's stakes have been checked
+        let cursor: u32 = env.storage().instance().get(&ExtDataKey::CleanupCursor).unwrap_or(0);
+        assert_with_error!(&env, offset == cursor, ExtMarketError::CleanupOutOfOrder);
+        
+        let balance = Self::token_balance(&env);
+        let claim_deadline: u64 = env.storage().instance().get(&DataKey::ClaimDeadline).unwrap_or(0);
+        let swept = claim_deadline > 0
+            && env.ledger().timestamp() > claim_deadline
+            && balance <= Self::owed_to_collector(&env);
+        
+        let mut ids: Vec<u64> = Self::livestream_ids(&env);
+        ids.append(&Self::removed_ids(&env));
+        
+        // Livestreams whose stakes are still owed to someone
+        let mut owed_ids: Vec<u64> = Vec::new(&env);
+        if !swept {
+            for id in Self::winning_ids(&env).iter() {
+                let weight: u64 = env.storage().persistent().get(&DataKey::ResolutionWeight(id)).unwrap_or(1);
+                if weight > 0 {
+                    owed_ids.push_back(id);
+                }
+            }
+            for id in ids.iter() {
+                if env.storage().persistent().has(&ExtDataKey::RefundableLivestream(id)) {
+                    owed_ids.push_back(id);
+                }
+            }
+        }
+        
+        let bettors = Self::get_bettors(env.clone(), offset, limit);
+        for user in bettors.iter() {
+            for id in ids.iter() {
+                let key = DataKey::Bets(user.clone(), id);
+                if owed_ids.contains(id) {
+                    let user_bet: i128 = env.storage().persistent().get(&key).unwrap_or(0);
+                    assert_with_error!(&env, user_bet == 0, ExtMarketError::UnclaimedFunds);
+                }
+                env.storage().persistent().remove(&key);
+            }
+            env.storage().persistent().remove(&DataKey::LastBetTime(user.clone()));
+        }
+        
+        let cursor = offset + bettors.len();
+        env.storage().instance().set(&ExtDataKey::CleanupCursor, &cursor);
+        
+        let total_bettors: u64 = Self::total_bettors(&env);
+        if cursor as u64 >= total_bettors {
+            for id in ids.iter() {
+                env.storage().persistent().remove(&DataKey::TotalBets(id));
+                env.storage().persistent().remove(&ExtDataKey::LivestreamBettorCount(id));
+            }
+        }
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "market_cleaned"),),
+            (offset, bettors.len())
+        );
+        
+        bettors.len()
+    }
+
+    /// Withdraw all accrued platform fees to the fee collector
+    pub fn withdraw_fees(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        let fee_collector: Address = env.storage().instance().get(&DataKey::FeeCollector).unwrap();
+        assert_with_error!(&env, caller == fee_collector, MarketError::NotFeeCollector);
+        
+        let accrued: i128 = env.storage().instance().get(&DataKey::AccruedFees).unwrap_or(0);
+        assert_with_error!(&env, accrued > 0, MarketError::NoFees);
+        
+        env.storage().instance().set(&DataKey::AccruedFees, &0i128);
+        
+        Self::send_tokens(&env, &fee_collector, accrued);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "fees_withdrawn"),),
+            (fee_collector, accrued)
+        );
+    }
+
+    /// Withdraw betting tokens released by payout-token claims to the fee collector
+    ///
+    /// Winners paid in the payout token leave their share of the betting pool
+    /// behind; it goes back to the collector who funded the collateral.
+    pub fn withdraw_released_collateral(env: Env, caller: Address) {
+        caller.require_auth();
+        
+        let fee_collector: Address = env.storage().instance().get(&DataKey::FeeCollector).unwrap();
+        assert_with_error!(&env, caller == fee_collector, MarketError::NotFeeCollector);
+        
+        let released: i128 = env.storage().instance().get(&ExtDataKey::ReleasedCollateral).unwrap_or(0);
+        assert_with_error!(&env, released > 0, ExtMarketError::NoReleasedCollateral);
+        
+        env.storage().instance().set(&ExtDataKey::ReleasedCollateral, &0i128);
+        
+        Self::send_tokens(&env, &fee_collector, released);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "collateral_withdrawn"),),
+            (fee_collector, released)
+        );
+    }
+
+    /// Send back a token that was transferred to the market by mistake
+    ///
+    /// The betting token can never be moved this way, so the pool stays intact.
+    pub fn rescue_tokens(env: Env, caller: Address, token: Address, to: Address, amount: i128) {
+        caller.require_auth();
+        
+        Self::assert_oracle(&env, &caller);
+        
+        let betting_token: Address = env.storage().instance().get(&DataKey::Token).unwrap();
+        assert_with_error!(&env, token != betting_token, MarketError::CannotRescueBettingToken);
+        assert_with_error!(&env, amount > 0, MarketError::InvalidAmount);
+        
+        // Payout collateral stays put until no winner can still claim it
+        if env.storage().instance().get::<_, Address>(&ExtDataKey::PayoutToken) == Some(token.clone()) {
+            assert_with_error!(&env, Self::get_max_liability(env.clone()) == 0, ExtMarketError::ClaimsOutstanding);
+        }
+        
+        token::Client::new(&env, &token).transfer(&env.current_contract_address(), &to, &amount);
+        
+        Self::bump_version(&env);
+        
+        env.events().publish(
+            (String::from_str(&env, "tokens_rescued"),),
+            (token, to, amount)
+        );
+    }
+
+    /// Get a counter that increases whenever the market's state changes
+    pub fn get_version(env: Env) -> u64 {
+        env.storage().instance().get(&DataKey::Version).unwrap_or(0)
+    }
+
+    /// Check whether `initialize` has been called
+    pub fn is_initialized(env: Env) -> bool {
+        env.storage().instance().has(&DataKey::State)
+    }
+
+    /// Get market information
+    ///
+    /// Returns (livestream_ids, question, state, winning_id, total_pool,
+    /// total_bettors, winning_pool); `winning_pool` is 0 until resolved.
+    pub fn get_market_info(env: Env) -> (Vec<u64>, String, State, u64, i128, u64, i128) {
+        let livestream_ids: Vec<u64> = Self::livestream_ids(&env);
+        let question: String = env.storage().instance().get(&DataKey::Question).unwrap_or(String::from_str(&env, ""));
+        let state: State = env.storage().instance().get(&DataKey::State).unwrap_or(State::Open);
+        let winning_id: u64 = env.storage().instance().get(&DataKey::WinningLivestreamId).unwrap_or(0);
+        let total_pool = Self::total_pool(&env);
+        let total_bettors: u64 = Self::total_bettors(&env);
+        // The close snapshot outlives `cleanup`, unlike the live totals
+        let winning_pool: i128 = if state == State::Resolved {
+            env.storage().persistent().get(&DataKey::ClosedSnapshot(winning_id)).unwrap_or(0)
+        } else {
+            0
+        };
+        
+        (livestream_ids, question, state, winning_id, total_pool, total_bettors, winning_pool)
+    }
+
+    /// Get the market state as lowercase text, e.g. "open" or "resolved"
+    pub fn get_state_label(env: Env) -> String {
+        let state: State = env.storage().instance().get(&DataKey::State).unwrap_or(State::Open);
+        let label = match state {
+            State::Open => "open",
+            State::Closed => "closed",
+            State::Resolved => "resolved",
+            State::Cancelled => "cancelled",
+            State::Draw => "draw",
+        };
+        String::from_str(&env, label)
+    }
+
+    /// Check whether a bet placed now would pass the state, pause and deadline checks
+    pub fn is_betting_open(env: Env) -> bool {
+        let state: State = env.storage().instance().get(&DataKey::State).unwrap_or(State::Closed);
+        if state != State::Open {
+            return false;
+        }
+        
+        let paused: bool = env.storage().instance().get(&DataKey::Paused).unwrap_or(false);
+        if paused {
+            return false;
+        }
+        
+        let deadline: u64 = env.storage().instance().get(&DataKey::BettingDeadline).unwrap_or(0);
+        deadline == 0 || env.ledger().timestamp() <= deadline
+    }
+
+    /// Seconds until the betting deadline; negative once it has passed,
+    /// `i64::MAX` when the market has no deadline
+    pub fn get_time_remaining(env: Env) -> i64 {
+        let deadline: u64 = env.storage().instance().get(&DataKey::BettingDeadline).unwrap_or(0);
+        if deadline == 0 {
+            return i64::MAX;
+        }
+        deadline as i64 - env.ledger().timestamp() as i64
+    }
+
+    /// Seconds since the market was created
+    pub fn get_market_age(env: Env) -> u64 {
+        let created_at: u64 = env.storage().instance().get(&DataKey::CreatedAt).unwrap_or(0);
+        env.ledger().timestamp().saturating_sub(created_at)
+    }
+
+    /// Get number of bets placed so far
+    pub fn get_bet_count(env: Env) -> u64 {
+        env.storage().instance().get(&DataKey::BetSeq).unwrap_or(0)
+    }
+
+    /// Get up to `count` of the latest bets as (user, livestream_id, amount, timestamp), newest first
+    ///
+    /// Only the last `RECENT_BETS_SIZE` bets are kept.
+    pub fn get_recent_bets(env: Env, count: u32) -> Vec<(Address, u64, i128, u64)> {
+        let seq: u64 = env.storage().instance().get(&DataKey::BetSeq).unwrap_or(0);
+        let count = (count as u64).min(RECENT_BETS_SIZE as u64).min(seq);
+        
+        let mut result: Vec<(Address, u64, i128, u64)> = Vec::new(&env);
+        for i in 0..count {
+            let slot = ((seq - 1 - i) % RECENT_BETS_SIZE as u64) as u32;
+            result.push_back(env.storage().persistent().get(&ExtDataKey::RecentBets(slot)).unwrap());
+        }
+        result
+    }
+
+    /// Get number of livestreams in the market
+    pub fn get_livestream_count(env: Env) -> u32 {
+        let livestream_ids: Vec<u64> = Self::livestream_ids(&env);
+        livestream_ids.len()
+    }
+
+    /// Get livestream betting data
+    pub fn get_livestream_bets(env: Env, livestream_id: u64) -> (i128, u64, bool) {
+        let livestream: LivestreamData = env.storage()
+            .persistent()
+            .get(&DataKey::Livestreams(livestream_id))
+            .unwrap_or_else(|| panic_with_error!(&env, MarketError::LivestreamNotFound));
+        
+        let amount: i128 = env.storage()
+            .persistent()
+            .get(&DataKey::TotalBets(livestream_id))
+            .unwrap_or(0);
+        
+        let total_pool = Self::total_pool(&env);
+        let percentage = if total_pool > 0 {
+            ((amount * 100) / total_pool) as u64
+        } else {
+            0
+        };
+        
+        (amount, percentage, livestream.active)
+    }
+
+    /// Get a livestream's stored data
+    pub fn get_livestream(env: Env, livestream_id: u64) -> LivestreamData {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Livestreams(livestream_id))
+            .unwrap_or_else(|| panic_with_error!(&env, MarketError::LivestreamNotFound))
+    }
+
+    /// Get a livestream's group; 0 if it's ungrouped
+    pub fn get_group(env: Env, livestream_id: u64) -> u64 {
+        env.storage().persistent().get(&ExtDataKey::LivestreamGroup(livestream_id)).unwrap_or(0)
+    }
+
+    /// Check whether betting on a livestream is enabled (not frozen)
+    pub fn is_livestream_betting_enabled(env: Env, livestream_id: u64) -> bool {
+        !env.storage().persistent().has(&ExtDataKey::LivestreamFrozen(livestream_id))
+    }
+
+    /// Get the ids of removed livestreams, oldest removal first
+    pub fn get_removed_livestreams(env: Env) -> Vec<u64> {
+        Self::removed_ids(&env)
+    }
+
+    /// Get the win probability in bps above which betting `amount` on a livestream has positive expected value
+    ///
+    /// A winning bet is paid (pool + amount) / (livestream pool + amount) per
+    /// unit staked, so the break-even probability is the inverse of that
+    /// ratio, rounded up. Fees are not taken into account.
+    pub fn get_break_even(env: Env, livestream_id: u64, amount: i128) -> u64 {
+        assert_with_error!(&env, amount > 0, MarketError::InvalidAmount);
+        
+        let total_pool = Self::total_pool(&env);
+        let livestream_pool: i128 = env.storage()
+            .persistent()
+            .get(&DataKey::TotalBets(livestream_id))
+            .unwrap_or(0);
+        
+        let stake = livestream_pool + amount;
+        let payout = total_pool + amount;
+        ((stake * 10_000 + payout - 1) / payout) as u64
+    }
+
+    /// Get platform fees accrued and not yet withdrawn
+    pub fn get_accrued_fees(env: Env) -> i128 {
+        env.storage().instance().get(&DataKey::AccruedFees).unwrap_or(0)
+    }
+
+    /// Get the platform and creator fees taken by all claims, including withdrawn fees
+    pub fn get_total_fees_collected(env: Env) -> i128 {
+        env.storage().instance().get(&ExtDataKey::TotalFees).unwrap_or(0)
+    }
+
+    /// Get the total amount bet by users referred by `referrer`
+    pub fn get_referral_volume(env: Env, referrer: Address) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::ReferralVolume(referrer))
+            .unwrap_or(0)
+    }
+
+    /// Get the factory that created this market
+    pub fn get_factory(env: Env) -> Address {
+        env.storage().instance().get(&DataKey::Factory).unwrap()
+    }
+
+    /// Get (platform_fee_bps, fee_collector, creator_fee_bps, creator)
+    pub fn get_fee_config(env: Env) -> (u32, Address, u32, Address) {
+        let fee_bps: u32 = env.storage().instance().get(&DataKey::FeeBps).unwrap_or(0);
+        let fee_collector: Address = env.storage().instance().get(&DataKey::FeeCollector).unwrap();
+        let creator_fee_bps: u32 = env.storage().instance().get(&DataKey::CreatorFeeBps).unwrap_or(0);
+        let creator: Address = env.storage().instance().get(&DataKey::Creator).unwrap();
+        (fee_bps, fee_collector, creator_fee_bps, creator)
+    }
+
+    /// Get the market's tags
+    pub fn get_tags(env: Env) -> Vec<String> {
+        env.storage().instance().get(&DataKey::Tags).unwrap_or(Vec::new(&env))
+    }
+
+    /// Check whether the market carries a tag
+    pub fn has_tag(env: Env, tag: String) -> bool {
+        let tags: Vec<String> = env.storage().instance().get(&DataKey::Tags).unwrap_or(Vec::new(&env));
+        tags.contains(&tag)
+    }
+
+    /// Check whether a user's claims are exempt from fees
+    pub fn is_fee_exempt(env: Env, who: Address) -> bool {
+        env.storage().persistent().get(&ExtDataKey::FeeExempt(who)).unwrap_or(false)
+    }
+
+    /// Get the betting token's decimals, for formatting amounts
+    pub fn get_token_decimals(env: Env) -> u32 {
+        env.storage().instance().get(&ExtDataKey::TokenDecimals).unwrap()
+    }
+
+    /// Get the token winners are paid in; None means the betting token
+    pub fn get_payout_token(env: Env) -> Option<Address> {
+        env.storage().instance().get(&ExtDataKey::PayoutToken)
+    }
+
+    /// Most the market still owes winners, in the payout token if one is set
+    ///
+    /// Before resolution the whole pool will be paid out; afterwards, whatever
+    /// claims haven't taken yet, until every winner has claimed or the claim
+    /// window closes. Fees are included, so with a payout token it's an upper
+    /// bound. Cancelled and drawn markets only refund stakes and owe nothing.
+    pub fn get_max_liability(env: Env) -> i128 {
+        let outstanding = match Self::state(&env) {
+            State::Open | State::Closed => Self::total_pool(&env),
+            State::Resolved => {
+                let claim_deadline: u64 = env.storage().instance().get(&DataKey::ClaimDeadline).unwrap_or(0);
+                let (claimed_count, total_winners) = Self::get_claim_stats(env.clone());
+                if (claim_deadline > 0 && env.ledger().timestamp() > claim_deadline) || claimed_count >= total_winners {
+                    0
+                } else {
+                    let paid_out: i128 = env.storage().instance().get(&ExtDataKey::PaidOut).unwrap_or(0);
+                    Self::total_pool(&env) - paid_out
+                }
+            }
+            State::Cancelled | State::Draw => 0,
+        };
+        
+        match env.storage().instance().get::<_, u32>(&ExtDataKey::PayoutRateBps) {
+            Some(rate_bps) => (outstanding * rate_bps as i128) / 10_000,
+            None => outstanding,
+        }
+    }
+
+    /// Check the market holds enough of its payout asset to cover `get_max_liability`
+    ///
+    /// Betting tokens owed to the fee collector don't count towards the balance.
+    pub fn is_solvent(env: Env) -> bool {
+        let available = match env.storage().instance().get::<_, Address>(&ExtDataKey::PayoutToken) {
+            Some(payout_token) => token::Client::new(&env, &payout_token).balance(&env.current_contract_address()),
+            None => Self::token_balance(&env) - Self::owed_to_collector(&env),
+        };
+        
+        Self::get_max_liability(env) <= available
+    }
+
+    /// Check whether `user` could claim a payout right now
+    ///
+    /// Covers weighted resolutions and the claim window, like `claim_payout`.
+    pub fn is_claimable(env: Env, user: Address) -> bool {
+        let claim_deadline: u64 = env.storage().instance().get(&DataKey::ClaimDeadline).unwrap_or(0);
+        Self::state(&env) == State::Resolved
+            && (claim_deadline == 0 || env.ledger().timestamp() <= claim_deadline)
+            && Self::has_winnings(&env, &user)
+    }
+
+    /// Get the market's creator
+    pub fn get_creator(env: Env) -> Address {
+        env.storage().instance().get(&DataKey::Creator).unwrap()
+    }
+
+    /// Get the market's admin
+    pub fn get_admin(env: Env) -> Address {
+        env.storage().instance().get(&DataKey::Admin).unwrap()
+    }
+
+    /// Get the market's oracle
+    pub fn get_oracle(env: Env) -> Address {
+        env.storage().instance().get(&DataKey::Oracle).unwrap()
+    }
+
+    /// Get when a bettor joined as (timestamp, bet sequence); (0, 0) for non-bettors
+    ///
+    /// Rankings use it to order equal stakes by who bet first.
+    pub fn get_first_bet(env: Env, user: Address) -> (u64, u64) {
+        env.storage().persistent().get(&ExtDataKey::FirstBet(user)).unwrap_or((0, 0))
+    }
+
+    /// Get an address's role: 0 = spectator, 1 = bettor, 2 = oracle
+    pub fn get_role(env: Env, who: Address) -> u32 {
+        let oracle: Address = env.storage().instance().get(&DataKey::Oracle).unwrap();
+        if who == oracle {
+            ROLE_ORACLE
+        } else if env.storage().persistent().has(&DataKey::HasBet(who)) {
+            ROLE_BETTOR
+        } else {
+            ROLE_SPECTATOR
+        }
+    }
+
+    /// Get a livestream's total bets as frozen when the market closed
+    pub fn get_closed_snapshot(env: Env, livestream_id: u64) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::ClosedSnapshot(livestream_id))
+            .unwrap_or(0)
+    }
+
+    /// Get user's bet on a specific livestream
+    pub fn get_user_bet(env: Env, user: Address, livestream_id: u64) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Bets(user, livestream_id))
+            .unwrap_or(0)
+    }
+
+    /// Get a user's stake across all current livestreams
+    pub fn get_user_total_stake(env: Env, user: Address) -> i128 {
+        let ids: Vec<u64> = Self::livestream_ids(&env);
+        
+        let mut staked: i128 = 0;
+        for id in ids.iter() {
+            staked += env.storage().persistent().get::<_, i128>(&DataKey::Bets(user.clone(), id)).unwrap_or(0);
+        }
+        
+        staked
+    }
+}
+
+impl PredictionMarket {
+    fn assert_factory_owner(env: &Env, caller: &Address) {
+        let factory: Address = env.storage().instance().get(&DataKey::Factory).unwrap();
+        let owner = FactoryClient::new(env, &factory).get_owner();
+        assert_with_error!(env, *caller == owner, MarketError::NotFactoryOwner);
+    }
+
+    fn assert_oracle(env: &Env, caller: &Address) {
+        let oracle: Address = env.storage().instance().get(&DataKey::Oracle).unwrap();
+        assert_with_error!(env, *caller == oracle, MarketError::NotOracle);
+    }
+
+    fn assert_factory(env: &Env, caller: &Address) {
+        let factory: Address = env.storage().instance().get(&DataKey::Factory).unwrap();
+        assert_with_error!(env, *caller == factory, MarketError::NotFactory);
+    }
+
+    fn assert_admin(env: &Env, caller: &Address) {
+        let admin: Address = env.storage().instance().get(&DataKey::Admin).unwrap();
+        assert_with_error!(env, *caller == admin, MarketError::NotAdmin);
+    }
+
+    /// Move to Closed and snapshot each livestream's pool
+    fn close(env: &Env) -> Vec<u64> {
+        env.storage().instance().set(&DataKey::State, &State::Closed);
+        env.storage().instance().set(&DataKey::ClosedAt, &env.ledger().timestamp());
+        
+        // Freeze each livestream's pool so resolution and payouts use close-time totals
+        let ids: Vec<u64> = Self::livestream_ids(env);
+        for i in 0..ids.len() {
+            let id = ids.get(i).unwrap();
+            let total: i128 = env.storage().persistent().get(&DataKey::TotalBets(id)).unwrap_or(0);
+            env.storage().persistent().set(&DataKey::ClosedSnapshot(id), &total);
+        }
+        
+        Self::bump_version(env);
+        ids
+    }
+
+    /// When unique titles are required, reject a title an active livestream already uses
+    fn assert_unique_title(env: &Env, title: &String) {
+        let require_unique: bool = env.storage().instance().get(&DataKey::RequireUniqueTitles).unwrap_or(false);
+        if !require_unique {
+            return;
+        }
+        
+        let ids: Vec<u64> = Self::livestream_ids(env);
+        for id in ids.iter() {
+            let livestream: LivestreamData = env.storage().persistent().get(&DataKey::Livestreams(id)).unwrap();
+            assert_with_error!(
+                env,
+                !(livestream.active && livestream.title == *title),
+                MarketError::DuplicateTitle
+            );
+        }
+    }
+
+    /// Add `user` to the bettor list if they aren't on it; returns whether they were added
+    fn track_bettor(env: &Env, user: &Address) -> bool {
+        let has_bet = env.storage().persistent().has(&DataKey::HasBet(user.clone()));
+        if has_bet {
+            return false;
+        }
+        env.storage().persistent().set(&DataKey::HasBet(user.clone()), &true);
+        let seq: u64 = env.storage().instance().get(&DataKey::BetSeq).unwrap_or(0);
+        env.storage().persistent().set(&ExtDataKey::FirstBet(user.clone()), &(env.ledger().timestamp(), seq));
+        
+        let total_bettors: u64 = Self::total_bettors(env);
+        Self::store_bettor(env, user, total_bettors as u32);
+        env.storage().instance().set(&DataKey::TotalBettors, &(total_bettors + 1));
+        true
+    }
+
+    /// Stop counting `user` as a bettor, undoing `track_bettor`
+    fn untrack_bettor(env: &Env, user: &Address) {
+        env.storage().persistent().remove(&DataKey::HasBet(user.clone()));
+        env.storage().persistent().remove(&ExtDataKey::FirstBet(user.clone()));
+        Self::remove_bettor(env, user);
+        let total_bettors: u64 = Self::total_bettors(env);
+        env.storage().instance().set(&DataKey::TotalBettors, &(total_bettors - 1));
+    }
+
+    /// Append a bettor at `position`, which must be the current bettor count
+    fn store_bettor(env: &Env, bettor: &Address, position: u32) {
+        let page_no = position / BETTOR_PAGE_SIZE;
+        let mut page: Vec<Address> = env.storage()
+            .persistent()
+            .get(&DataKey::BettorPage(page_no))
+            .unwrap_or(Vec::new(env));
+        page.push_back(bettor.clone());
+        env.storage().persistent().set(&DataKey::BettorPage(page_no), &page);
+        env.storage().persistent().set(&DataKey::BettorIndex(bettor.clone()), &position);
+    }
+
+    /// Remove a bettor by moving the last bettor into their slot, keeping pages dense
+    fn remove_bettor(env: &Env, bettor: &Address) {
+        let position: u32 = env.storage().persistent().get(&DataKey::BettorIndex(bettor.clone())).unwrap();
+        let total_bettors: u64 = Self::total_bettors(env);
+        let last = total_bettors as u32 - 1;
+        
+        let last_page_no = last / BETTOR_PAGE_SIZE;
+        let mut last_page: Vec<Address> = env.storage().persistent().get(&DataKey::BettorPage(last_page_no)).unwrap();
+        let moved = last_page.pop_back().unwrap();
+        
+        if position != last {
+            let page_no = position / BETTOR_PAGE_SIZE;
+            if page_no == last_page_no {
+                last_page.set(position % BETTOR_PAGE_SIZE, moved.clone());
+            } else {
+                let mut page: Vec<Address> = env.storage().persistent().get(&DataKey::BettorPage(page_no)).unwrap();
+                page.set(position % BETTOR_PAGE_SIZE, moved.clone());
+                env.storage().persistent().set(&DataKey::BettorPage(page_no), &page);
+            }
+            env.storage().persistent().set(&DataKey::BettorIndex(moved), &position);
+        }
+        
+        if last_page.is_empty() {
+            env.storage().persistent().remove(&DataKey::BettorPage(last_page_no));
+        } else {
+            env.storage().persistent().set(&DataKey::BettorPage(last_page_no), &last_page);
+        }
+        env.storage().persistent().remove(&DataKey::BettorIndex(bettor.clone()));
+    }
+
+    /// Title for a livestream auto-added by a bet, e.g. "Project #42"
+    fn auto_title(env: &Env, livestream_id: u64) -> String {
+        const PREFIX: &[u8] = b"Project #";
+        // u64::MAX has 20 digits
+        let mut buf = [0u8; PREFIX.len() + 20];
+        buf[..PREFIX.len()].copy_from_slice(PREFIX);
+        
+        let mut digits = [0u8; 20];
+        let mut n = livestream_id;
+        let mut len = 0;
+        loop {
+            digits[len] = b'0' + (n % 10) as u8;
+            len += 1;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
+        for i in 0..len {
+            buf[PREFIX.len() + i] = digits[len - 1 - i];
+        }
+        
+        String::from_bytes(env, &buf[..PREFIX.len() + len])
+    }
+
+    /// Close, then tell the factory and emit `market_closed`
+    fn close_and_notify(env: &Env) {
+        let ids = Self::close(env);
+        
+        // Best-effort: a market initialized with a non-factory address still closes
+        let factory: Address = env.storage().instance().get(&DataKey::Factory).unwrap();
+        let factory_client = FactoryClient::new(env, &factory);
+        let _ = factory_client.try_notify_market_closed(&env.current_contract_address(), &ids);
+        
+        // Pool and bettors are final once betting stops, so report them exactly once here
+        let total_pool = Self::total_pool(env);
+        let total_bettors: u64 = Self::total_bettors(env);
+        let _ = factory_client.try_report_stats(&env.current_contract_address(), &total_pool, &total_bettors);
+        
+        env.events().publish(
+            (String::from_str(env, "market_closed"),),
+            env.ledger().timestamp()
+        );
+    }
+
+    /// Ensure a livestream can be declared the winner
+    fn validate_winner(env: &Env, winning_livestream_id: u64) {
+        let _livestream: LivestreamData = env.storage()
+            .persistent()
+            .get(&DataKey::Livestreams(winning_livestream_id))
+            .unwrap_or_else(|| panic_with_error!(env, MarketError::InvalidLivestream));
+        
+        // Removed livestreams keep their stored data, so check the current list too
+        let ids: Vec<u64> = Self::livestream_ids(env);
+        assert_with_error!(env, ids.contains(winning_livestream_id), ExtMarketError::WinnerNotInMarket);
+        
+        let total_bets: i128 = env.storage()
+            .persistent()
+            .get(&DataKey::ClosedSnapshot(winning_livestream_id))
+            .unwrap_or(0);
+        assert_with_error!(env, total_bets > 0, MarketError::NoBetsOnLivestream);
+    }
+
+    /// Pay out `user`'s winnings to `recipient`, net of fees
+    fn pay_winnings(env: &Env, user: &Address, recipient: &Address) {
+        // Calculate payout; this also zeroes the user's winning bets
+        let gross_payout = Self::take_winnings(env, user);
+        let paid_out: i128 = env.storage().instance().get(&ExtDataKey::PaidOut).unwrap_or(0);
+        env.storage().instance().set(&ExtDataKey::PaidOut, &(paid_out + gross_payout));
+        let claimed_count: u64 = env.storage().instance().get(&ExtDataKey::ClaimedCount).unwrap_or(0);
+        env.storage().instance().set(&ExtDataKey::ClaimedCount, &(claimed_count + 1));
+        
+        // Platform fee accrues in the contract until the collector withdraws it
+        let exempt: bool = env.storage().persistent().get(&ExtDataKey::FeeExempt(user.clone())).unwrap_or(false);
+        let fee_bps: u32 = if exempt { 0 } else { env.storage().instance().get(&DataKey::FeeBps).unwrap_or(0) };
+        let fee = (gross_payout * fee_bps as i128) / 10_000;
+        
+        if fee > 0 {
+            let accrued: i128 = env.storage().instance().get(&DataKey::AccruedFees).unwrap_or(0);
+            env.storage().instance().set(&DataKey::AccruedFees, &(accrued + fee));
+        }
+        
+        // Creator fee is paid out to the creator on every claim
+        let creator_fee_bps: u32 = if exempt { 0 } else { env.storage().instance().get(&DataKey::CreatorFeeBps).unwrap_or(0) };
+        let creator_fee = (gross_payout * creator_fee_bps as i128) / 10_000;
+        
+        let mut payout = gross_payout - fee - creator_fee;
+        
+        let total_fees: i128 = env.storage().instance().get(&ExtDataKey::TotalFees).unwrap_or(0);
+        env.storage().instance().set(&ExtDataKey::TotalFees, &(total_fees + fee + creator_fee));
+        
+        // Never dip into tokens owed to the collector; a rounding-sized shortfall
+        // comes out of this payout, anything larger is an accounting bug
+        let available = Self::token_balance(env) - Self::owed_to_collector(env);
+        
+        // Transfer payout, converted into the payout token if one is configured.
+        // The betting tokens it replaces are released to the fee collector, who funds the collateral
+        match env.storage().instance().get::<_, Address>(&ExtDataKey::PayoutToken) {
+            Some(payout_token) => {
+                assert_with_error!(env, creator_fee <= available, MarketError::InsufficientPool);
+                
+                let rate_bps: u32 = env.storage().instance().get(&ExtDataKey::PayoutRateBps).unwrap();
+                let mut converted = (payout * rate_bps as i128) / 10_000;
+                
+                let payout_client = token::Client::new(env, &payout_token);
+                let shortfall = converted - payout_client.balance(&env.current_contract_address());
+                if shortfall > 0 {
+                    assert_with_error!(env, shortfall <= PAYOUT_ROUNDING_TOLERANCE, MarketError::InsufficientPool);
+                    converted -= shortfall;
+                }
+                
+                let released: i128 = env.storage().instance().get(&ExtDataKey::ReleasedCollateral).unwrap_or(0);
+                env.storage().instance().set(&ExtDataKey::ReleasedCollateral, &(released + payout));
+                payout_client.transfer(&env.current_contract_address(), recipient, &converted);
+                payout = converted;
+            }
+            None => {
+                let shortfall = payout + creator_fee - available;
+                if shortfall > 0 {
+                    assert_with_error!(env, shortfall <= PAYOUT_ROUNDING_TOLERANCE, MarketError::InsufficientPool);
+                    payout -= shortfall;
+                }
+                Self::send_tokens(env, recipient, payout);
+            }
+        }
+        
+        if creator_fee > 0 {
+            let creator: Address = env.storage().instance().get(&DataKey::Creator).unwrap();
+            Self::send_tokens(env, &creator, creator_fee);
+        }
+        
+        Self::bump_version(env);
+        
+        env.events().publish(
+            (String::from_str(env, "payout_claimed"),),
+            (user.clone(), recipient.clone(), payout, env.ledger().timestamp())
+        );
+    }
+
+    /// Betting tokens held for the fee collector: accrued fees plus released collateral
+    fn owed_to_collector(env: &Env) -> i128 {
+        let accrued: i128 = env.storage().instance().get(&DataKey::AccruedFees).unwrap_or(0);
+        let released: i128 = env.storage().instance().get(&ExtDataKey::ReleasedCollateral).unwrap_or(0);
+        accrued + released
+    }
+
+    /// Livestreams paying out: the weighted set, or just the winner
+    fn winning_ids(env: &Env) -> Vec<u64> {
+        let weighted_ids: Option<Vec<u64>> = env.storage().instance().get(&DataKey::WeightedIds);
+        weighted_ids.unwrap_or_else(|| {
+            let winning_id: u64 = env.storage().instance().get(&DataKey::WinningLivestreamId).unwrap();
+            Vec::from_array(env, [winning_id])
+        })
+    }
+
+    /// Check whether `user` holds an unclaimed winning bet
+    fn has_winnings(env: &Env, user: &Address) -> bool {
+        let winning_ids = Self::winning_ids(env);
+        
+        winning_ids.iter().any(|id| {
+            let weight: u64 = env.storage().persistent().get(&DataKey::ResolutionWeight(id)).unwrap_or(1);
+            let user_bet: i128 = env.storage().persistent().get(&DataKey::Bets(user.clone(), id)).unwrap_or(0);
+            weight > 0 && user_bet > 0
+        })
+    }
+
+    /// Gross winnings for `user`, zeroing the bets they are paid for
+    fn take_winnings(env: &Env, user: &Address) -> i128 {
+        let total_pool = Self::total_pool(env);
+        
+        let weighted_ids: Option<Vec<u64>> = env.storage().instance().get(&DataKey::WeightedIds);
+        if let Some(weighted_ids) = weighted_ids {
+            let mut user_weighted: i128 = 0;
+            let mut weighted_total: i128 = 0;
+            let mut paid_ids: u64 = 0;
+            for id in weighted_ids.iter() {
+                let weight: u64 = env.storage().persistent().get(&DataKey::ResolutionWeight(id)).unwrap();
+                let snapshot: i128 = env.storage().persistent().get(&DataKey::ClosedSnapshot(id)).unwrap_or(0);
+                weighted_total += snapshot * weight as i128;
+                
+                let user_bet: i128 = env.storage()
+                    .persistent()
+                    .get(&DataKey::Bets(user.clone(), id))
+                    .unwrap_or(0);
+                if weight > 0 && user_bet > 0 {
+                    user_weighted += user_bet * weight as i128;
+                    paid_ids += 1;
+                    env.storage().persistent().set(&DataKey::Bets(user.clone(), id), &0i128);
+                }
+            }
+            assert_with_error!(env, user_weighted > 0, MarketError::NoWinningBet);
+            
+            // `count_winners` counted this user once per winning livestream they backed
+            if paid_ids > 1 {
+                let total_winners: u64 = env.storage().instance().get(&ExtDataKey::TotalWinners).unwrap();
+                env.storage().instance().set(&ExtDataKey::TotalWinners, &(total_winners - (paid_ids - 1)));
+            }
+            return (user_weighted * total_pool) / weighted_total;
+        }
+        
+        let winning_id: u64 = env.storage().instance().get(&DataKey::WinningLivestreamId).unwrap();
+        
+        let user_bet: i128 = env.storage()
+            .persistent()
+            .get(&DataKey::Bets(user.clone(), winning_id))
+            .unwrap_or(0);
+        assert_with_error!(env, user_bet > 0, MarketError::NoWinningBet);
+        
+        let multiplier: i128 = env.storage().instance().get(&ExtDataKey::PayoutMultiplier).unwrap();
+        
+        env.storage().persistent().set(&DataKey::Bets(user.clone(), winning_id), &0i128);
+        (user_bet * multiplier) / PAYOUT_SCALE
+    }
+
+    /// Resolve a single-oracle market on the oracle's word
+    fn resolve_by_oracle(env: &Env, caller: &Address, winning_livestream_id: u64) {
+        Self::assert_oracle(env, caller);
+        
+        let state = Self::state(env);
+        assert_with_error!(env, state == State::Closed, MarketError::NotClosed);
+        
+        let threshold: u32 = env.storage().instance().get(&DataKey::Threshold).unwrap_or(0);
+        assert_with_error!(env, threshold == 0, MarketError::MultiOracleMarket);
+        
+        Self::finalize_resolution(env, caller, winning_livestream_id);
+    }
+
+    /// Move a closed market to Resolved with the given winner
+    fn finalize_resolution(env: &Env, resolver: &Address, winning_livestream_id: u64) {
+        Self::validate_winner(env, winning_livestream_id);
+        
+        env.storage().instance().set(&DataKey::WinningLivestreamId, &winning_livestream_id);
+        Self::mark_resolved(env, resolver);
+        
+        // Freeze the payout rate so claims don't depend on later pool changes
+        let total_pool = Self::total_pool(env);
+        let winning_pool: i128 = env.storage()
+            .persistent()
+            .get(&DataKey::ClosedSnapshot(winning_livestream_id))
+            .unwrap();
+        env.storage().instance().set(&ExtDataKey::PayoutMultiplier, &(total_pool * PAYOUT_SCALE / winning_pool));
+        
+        env.events().publish(
+            (String::from_str(env, "market_resolved"),),
+            (winning_livestream_id, env.ledger().timestamp())
+        );
+    }
+
+    /// Move to Resolved and start the claim window, if any
+    ///
+    /// Fails if the pool or bettor count is below the market's minimum; such
+    /// markets have to be cancelled instead.
+    fn mark_resolved(env: &Env, resolver: &Address) {
+        let min_pool: i128 = env.storage().instance().get(&DataKey::MinPool).unwrap_or(0);
+        let total_pool = Self::total_pool(env);
+        assert_with_error!(env, total_pool >= min_pool, MarketError::PoolBelowMinimum);
+        
+        let min_bettors: u64 = env.storage().instance().get(&ExtDataKey::MinBettors).unwrap_or(0);
+        let total_bettors: u64 = Self::total_bettors(env);
+        assert_with_error!(env, total_bettors >= min_bettors, ExtMarketError::NotEnoughBettors);
+        
+        env.storage().instance().set(&DataKey::State, &State::Resolved);
+        env.storage().instance().set(&DataKey::ResolvedAt, &env.ledger().timestamp());
+        env.storage().instance().set(&ExtDataKey::Resolver, resolver);
+        Self::refund_removed_livestreams(env);
+        Self::count_winners(env);
+        
+        let claim_window: u64 = env.storage().instance().get(&DataKey::ClaimWindow).unwrap_or(0);
+        if claim_window > 0 {
+            env.storage().instance().set(&DataKey::ClaimDeadline, &(env.ledger().timestamp() + claim_window));
+        }
+        
+        Self::bump_version(env);
+    }
+
+    /// Track how many users hold a stake on a livestream
+    fn adjust_livestream_bettors(env: &Env, livestream_id: u64, delta: i64) {
+        let count: u64 = env.storage()
+            .persistent()
+            .get(&ExtDataKey::LivestreamBettorCount(livestream_id))
+            .unwrap_or(0);
+        env.storage()
+            .persistent()
+            .set(&ExtDataKey::LivestreamBettorCount(livestream_id), &count.saturating_add_signed(delta));
+    }
+
+    /// Cache the number of winners for `get_claim_stats`
+    ///
+    /// Winners are distinct bettors, matching one claim each. Weighted markets
+    /// start from the backer counts of every paying livestream, which counts a
+    /// user who backed several of them more than once; `take_winnings` corrects
+    /// the total as those users claim, so it is exact once everyone has.
+    fn count_winners(env: &Env) {
+        let winners: u64 = Self::winning_ids(env)
+            .iter()
+            .filter(|id| env.storage().persistent().get(&DataKey::ResolutionWeight(*id)).unwrap_or(1u64) > 0)
+            .map(|id| env.storage().persistent().get::<_, u64>(&ExtDataKey::LivestreamBettorCount(id)).unwrap_or(0))
+            .sum();
+        env.storage().instance().set(&ExtDataKey::TotalWinners, &winners);
+    }
+
+    /// Set aside stakes on removed livestreams that can't win, for `claim_refund`
+    ///
+    /// Those stakes leave `TotalPool`, so winners are paid from the rest.
+    fn refund_removed_livestreams(env: &Env) {
+        let removed: Vec<u64> = Self::removed_ids(env);
+        let winning_id: u64 = env.storage().instance().get(&DataKey::WinningLivestreamId).unwrap_or(0);
+        
+        let mut refundable: i128 = 0;
+        for id in removed.iter() {
+            let weight: u64 = env.storage().persistent().get(&DataKey::ResolutionWeight(id)).unwrap_or(0);
+            if id == winning_id || weight > 0 {
+                continue;
+            }
+            
+            let total_bets: i128 = env.storage().persistent().get(&DataKey::TotalBets(id)).unwrap_or(0);
+            if total_bets > 0 {
+                env.storage().persistent().set(&ExtDataKey::RefundableLivestream(id), &true);
+                refundable += total_bets;
+            }
+        }
+        
+        if refundable > 0 {
+            let total_pool = Self::total_pool(env);
+            env.storage().instance().set(&DataKey::TotalPool, &(total_pool - refundable));
+        }
+    }
+
+    /// Transfer betting tokens out of the market
+    fn send_tokens(env: &Env, to: &Address, amount: i128) {
+        let token_address: Address = env.storage().instance().get(&DataKey::Token).unwrap();
+        token::Client::new(env, &token_address).transfer(&env.current_contract_address(), to, &amount);
+    }
+
+    /// Betting tokens held by the market
+    fn token_balance(env: &Env) -> i128 {
+        let token_address: Address = env.storage().instance().get(&DataKey::Token).unwrap();
+        token::Client::new(env, &token_address).balance(&env.current_contract_address())
+    }
+
+    /// Total tokens staked, plus any bonus, that winners share
+    fn total_pool(env: &Env) -> i128 {
+        env.storage().instance().get(&DataKey::TotalPool).unwrap_or(0)
+    }
+
+    /// Stakes on the market's current livestreams, leaving out bonuses and removed livestreams
+    fn active_stakes(env: &Env) -> i128 {
+        Self::livestream_ids(env)
+            .iter()
+            .map(|id| env.storage().persistent().get::<_, i128>(&DataKey::TotalBets(id)).unwrap_or(0))
+            .sum()
+    }
+
+    /// Livestreams currently in the market
+    fn livestream_ids(env: &Env) -> Vec<u64> {
+        env.storage().instance().get(&DataKey::LivestreamIds).unwrap_or(Vec::new(env))
+    }
+
+    /// Livestreams removed from the market, in removal order
+    fn removed_ids(env: &Env) -> Vec<u64> {
+        env.storage().instance().get(&DataKey::RemovedIds).unwrap_or(Vec::new(env))
+    }
+
+    /// Number of distinct bettors currently tracked
+    fn total_bettors(env: &Env) -> u64 {
+        env.storage().instance().get(&DataKey::TotalBettors).unwrap_or(0)
+    }
+
+    /// Current lifecycle state
+    fn state(env: &Env) -> State {
+        env.storage().instance().get(&DataKey::State).unwrap()
+    }
+
+    /// Mark that the market's state changed, for clients polling `get_version`
+    fn bump_version(env: &Env) {
+        let version: u64 = env.storage().instance().get(&DataKey::Version).unwrap_or(0);
+        env.storage().instance().set(&DataKey::Version, &(version + 1));
+    }
+
+    /// Detach forked livestreams from the market and send their pool to `destination`
+    ///
+    /// Their bets stay with each bettor until `fork_out` pages through them.
+    fn start_fork(env: &Env, livestream_ids: &Vec<u64>, destination: &Address) {
+        let state = Self::state(env);
+        assert_with_error!(env, state == State::Open, MarketError::NotOpen);
+        
+        let mut ids: Vec<u64> = Self::livestream_ids(env);
+        for id in livestream_ids.iter() {
+            let index = ids.first_index_of(id)
+                .unwrap_or_else(|| panic_with_error!(env, MarketError::LivestreamNotFound));
+            ids.remove(index);
+        }
+        env.storage().instance().set(&DataKey::LivestreamIds, &ids);
+        
+        let mut moved_total: i128 = 0;
+        for id in livestream_ids.iter() {
+            moved_total += env.storage().persistent().get::<_, i128>(&DataKey::TotalBets(id)).unwrap_or(0);
+            env.storage().persistent().remove(&DataKey::TotalBets(id));
+            env.storage().persistent().remove(&ExtDataKey::LivestreamBettorCount(id));
+            
+            let mut livestream: LivestreamData = env.storage().persistent().get(&DataKey::Livestreams(id)).unwrap();
+            livestream.active = false;
+            env.storage().persistent().set(&DataKey::Livestreams(id), &livestream);
+            env.storage().persistent().set(&ExtDataKey::LivestreamFrozen(id), &true);
+        }
+        
+        if moved_total > 0 {
+            let total_pool = Self::total_pool(env);
+            env.storage().instance().set(&DataKey::TotalPool, &(total_pool - moved_total));
+            
+            Self::send_tokens(env, destination, moved_total);
+        }
+        
+        env.events().publish(
+            (String::from_str(env, "livestreams_forked"),),
+            (livestream_ids.clone(), destination.clone(), moved_total)
+        );
+    }
+
+    /// Check positions can still change: open, not paused and before the betting deadline
+    fn assert_trading(env: &Env) {
+        let state = Self::state(env);
+        assert_with_error!(env, state == State::Open, MarketError::NotOpen);
+        
+        let paused: bool = env.storage().instance().get(&DataKey::Paused).unwrap_or(false);
+        assert_with_error!(env, !paused, MarketError::Paused);
+        
+        let deadline: u64 = env.storage().instance().get(&DataKey::BettingDeadline).unwrap_or(0);
+        let before_deadline = deadline == 0 || env.ledger().timestamp() <= deadline;
+        assert_with_error!(env, before_deadline, MarketError::DeadlinePassed);
+    }
+
+    /// Validate a bet, pull the tokens in and update all bet accounting
+    fn record_bet(env: &Env, user: &Address, livestream_id: u64, amount: i128) {
+        Self::assert_trading(env);
+        
+        assert_with_error!(env, amount > 0, MarketError::InvalidAmount);
+        assert_with_error!(env, livestream_id > 0, MarketError::InvalidLivestream);
+        
+        let bet_tick: i128 = env.storage().instance().get(&ExtDataKey::BetTick).unwrap_or(0);
+        if bet_tick > 0 {
+            assert_with_error!(env, amount % bet_tick == 0, ExtMarketError::AmountNotMultipleOfTick);
+        }
+        
+        let frozen = env.storage().persistent().has(&ExtDataKey::LivestreamFrozen(livestream_id));
+        assert_with_error!(env, !frozen, ExtMarketError::LivestreamFrozen);
+        
+        let cooldown: u64 = env.storage().instance().get(&DataKey::BetCooldown).unwrap_or(0);
+        if cooldown > 0 {
+            let last_bet: Option<u64> = env.storage().persistent().get(&DataKey::LastBetTime(user.clone()));
+            if let Some(last_bet) = last_bet {
+                assert_with_error!(
+                    env,
+                    env.ledger().timestamp() >= last_bet + cooldown,
+                    MarketError::BetCooldownActive
+                );
+            }
+            env.storage().persistent().set(&DataKey::LastBetTime(user.clone()), &env.ledger().timestamp());
+        }
+        
+        let max_user_stake: i128 = env.storage().instance().get(&DataKey::MaxUserStake).unwrap_or(0);
+        if max_user_stake > 0 {
+            let staked = Self::get_user_total_stake(env.clone(), user.clone());
+            assert_with_error!(env, staked + amount <= max_user_stake, MarketError::UserStakeCapExceeded);
+        }
+        
+        // Auto-add livestream if it doesn't exist
+        let exists = env.storage().persistent().has(&DataKey::Livestreams(livestream_id));
+        if !exists {
+            let default_title = Self::auto_title(env, livestream_id);
+            
+            let livestream = LivestreamData {
+                id: livestream_id,
+                title: default_title,
+                active: true,
+                added_at: env.ledger().timestamp(),
+            };
+            
+            env.storage().persistent().set(&DataKey::Livestreams(livestream_id), &livestream);
+            
+            let mut ids: Vec<u64> = Self::livestream_ids(env);
+            ids.push_back(livestream_id);
+            env.storage().instance().set(&DataKey::LivestreamIds, &ids);
+        }
+        
+        // Transfer tokens from user to contract
+        let token_address: Address = env.storage().instance().get(&DataKey::Token).unwrap();
+        let token_client = token::Client::new(env, &token_address);
+        token_client.transfer(user, env.current_contract_address(), &amount);
+        
+        // Track new bettor
+        if Self::track_bettor(env, user) {
+            env.events().publish(
+                (String::from_str(env, "new_bettor"),),
+                (user.clone(), livestream_id)
+            );
+        }
+        
+        // Update bet amounts
+        let current_bet = env.storage()
+            .persistent()
+            .get(&DataKey::Bets(user.clone(), livestream_id))
+            .unwrap_or(0i128);
+        env.storage()
+            .persistent()
+            .set(&DataKey::Bets(user.clone(), livestream_id), &(current_bet + amount));
+        if current_bet == 0 {
+            Self::adjust_livestream_bettors(env, livestream_id, 1);
+        }
+        
+        let current_total = env.storage()
+            .persistent()
+            .get(&DataKey::TotalBets(livestream_id))
+            .unwrap_or(0i128);
+        env.storage()
+            .persistent()
+            .set(&DataKey::TotalBets(livestream_id), &(current_total + amount));
+        
+        let total_pool = Self::total_pool(env);
+        env.storage().instance().set(&DataKey::TotalPool, &(total_pool + amount));
+        
+        // Both caps weigh this livestream against stakes on the others; bonuses
+        // and removed livestreams don't balance anything
+        let max_concentration_bps: u32 = env.storage().instance().get(&ExtDataKey::MaxConcentrationBps).unwrap_or(0);
+        let max_imbalance_bps: u32 = env.storage().instance().get(&ExtDataKey::MaxImbalanceBps).unwrap_or(0);
+        let new_total = current_total + amount;
+        let stakes = if max_concentration_bps > 0 || max_imbalance_bps > 0 {
+            Self::active_stakes(env)
+        } else {
+            0
+        };
+        
+        // A livestream holding all the stakes can't be balanced yet, so the cap
+        // only applies once there is money elsewhere
+        if max_concentration_bps > 0 && new_total < stakes {
+            assert_with_error!(
+                env,
+                new_total * 10_000 / stakes <= max_concentration_bps as i128,
+                ExtMarketError::ConcentrationExceeded
+            );
+        }
+        
+        // Same exemption for the balanced-book cap: this livestream may exceed
+        // the other stakes by at most `max_imbalance_bps` of them
+        let others = stakes - new_total;
+        if max_imbalance_bps > 0 && others > 0 {
+            assert_with_error!(
+                env,
+                (new_total - others) * 10_000 <= others * max_imbalance_bps as i128,
+                ExtMarketError::BookUnbalanced
+            );
+        }
+        
+        // Sequence number lets indexers detect missed or reordered bet events
+        let seq: u64 = env.storage().instance().get(&DataKey::BetSeq).unwrap_or(0) + 1;
+        env.storage().instance().set(&DataKey::BetSeq, &seq);
+        env.storage().persistent().set(
+            &ExtDataKey::RecentBets(((seq - 1) % RECENT_BETS_SIZE as u64) as u32),
+            &(user.clone(), livestream_id, amount, env.ledger().timestamp())
+        );
+        Self::bump_version(env);
+        
+        let emit_bet_events: bool = env.storage().instance().get(&ExtDataKey::EmitBetEvents).unwrap_or(true);
+        if emit_bet_events {
+            // The livestream is a topic so indexers can follow a single contestant
+            env.events().publish(
+                (String::from_str(env, "bet_placed"), livestream_id),
+                (user.clone(), livestream_id, amount, env.ledger().timestamp(), seq)
+            );
+        }
     }
 }
 
@@ -1,7 +1,86 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{testutils::Address as _, Address, BytesN, Env, String, Vec};
+use soroban_sdk::{
+    testutils::{Address as _, Events, Ledger},
+    token, Address, Bytes, Env, String, TryFromVal, Val, Vec,
+};
+
+fn create_token(env: &Env) -> Address {
+    let admin = Address::generate(env);
+    env.register_stellar_asset_contract_v2(admin).address()
+}
+
+fn mint(env: &Env, token: &Address, to: &Address, amount: i128) {
+    token::StellarAssetClient::new(env, token).mint(to, &amount);
+}
+
+/// Data of the first event named `name` emitted by the last invocation
+fn find_event(env: &Env, name: &str) -> Option<Val> {
+    let expected = String::from_str(env, name);
+    for (_, topics, data) in env.events().all().iter() {
+        let topic = String::try_from_val(env, &topics.get(0).unwrap());
+        if topic == Ok(expected.clone()) {
+            return Some(data);
+        }
+    }
+    None
+}
+
+fn default_config(env: &Env) -> MarketConfig {
+    MarketConfig {
+        fee_bps: 0,
+        fee_collector: Address::generate(env),
+        betting_deadline: 0,
+        oracles: Vec::new(env),
+        threshold: 0,
+        creator_fee_bps: 0,
+        creator: Address::generate(env),
+        open_timeout: 0,
+        max_user_stake: 0,
+        tags: Vec::new(env),
+        bet_cooldown: 0,
+        require_unique_titles: false,
+        claim_window: 0,
+        oracle_contract: None,
+        admin: None,
+        min_pool: 0,
+        payout_token: None,
+        payout_rate_bps: 0,
+        draw_fee_bps: 0,
+        min_bettors: 0,
+        emit_bet_events: true,
+        reopen_window: 0,
+        max_concentration_bps: 0,
+        bet_tick: 0,
+        max_imbalance_bps: 0,
+    }
+}
+
+fn setup_market<'a>(env: &Env, livestream_ids: Vec<u64>) -> (PredictionMarketClient<'a>, Address, Address) {
+    setup_market_with_config(env, livestream_ids, default_config(env))
+}
+
+fn setup_market_with_config<'a>(
+    env: &Env,
+    livestream_ids: Vec<u64>,
+    config: MarketConfig,
+) -> (PredictionMarketClient<'a>, Address, Address) {
+    let contract_id = env.register(PredictionMarket, ());
+    let client = PredictionMarketClient::new(env, &contract_id);
+
+    let oracle = Address::generate(env);
+    let factory = Address::generate(env);
+    let token = create_token(env);
+    let question = String::from_str(env, "Which livestream will win?");
+    let mut livestream_titles = Vec::new(env);
+    for _ in livestream_ids.iter() {
+        livestream_titles.push_back(String::from_str(env, "Livestream"));
+    }
+
+    client.initialize(&livestream_ids, &question, &livestream_titles, &oracle, &factory, &token, &config);
+    (client, oracle, token)
+}
 
 #[test]
 fn test_initialize() {
@@ -9,12 +88,9 @@ fn test_initialize() {
     let contract_id = env.register(PredictionMarket, ());
     let client = PredictionMarketClient::new(&env, &contract_id);
 
-    // Create test addresses using BytesN
-    let oracle_bytes = [1u8; 32];
-    let oracle = Address::from_contract_id(&env, &BytesN::from_array(&env, &oracle_bytes));
-    
-    let factory_bytes = [2u8; 32];
-    let factory = Address::from_contract_id(&env, &BytesN::from_array(&env, &factory_bytes));
+    let oracle = Address::generate(&env);
+    let factory = Address::generate(&env);
+    let token = create_token(&env);
     let question = String::from_str(&env, "Which livestream will win?");
     
     let livestream_ids = Vec::from_array(&env, [1u64, 2u64]);
@@ -26,19 +102,2268 @@ fn test_initialize() {
         ],
     );
 
+    assert!(!client.is_initialized());
     client.initialize(
         &livestream_ids,
         &question,
         &livestream_titles,
         &oracle,
         &factory,
+        &token,
+        &default_config(&env),
     );
 
-    let (ids, retrieved_question, state, winning_id, total_pool, total_bettors) = client.get_market_info();
+    assert!(client.is_initialized());
+
+    let (ids, retrieved_question, state, winning_id, total_pool, total_bettors, winning_pool) = client.get_market_info();
     assert_eq!(ids.len(), 2);
     assert_eq!(retrieved_question, question);
     assert_eq!(state, State::Open);
     assert_eq!(winning_id, 0);
     assert_eq!(total_pool, 0);
     assert_eq!(total_bettors, 0);
+    assert_eq!(winning_pool, 0);
+    assert_eq!(client.get_oracle(), oracle);
+    assert_eq!(client.get_factory(), factory);
+}
+
+#[test]
+fn test_initialize_twice() {
+    let env = Env::default();
+    let contract_id = env.register(PredictionMarket, ());
+    let client = PredictionMarketClient::new(&env, &contract_id);
+
+    let oracle = Address::generate(&env);
+    let factory = Address::generate(&env);
+    let token = create_token(&env);
+    let question = String::from_str(&env, "Which livestream will win?");
+    let livestream_ids = Vec::from_array(&env, [1u64]);
+    let livestream_titles = Vec::from_array(&env, [String::from_str(&env, "Livestream 1")]);
+
+    let config = default_config(&env);
+
+    client.initialize(&livestream_ids, &question, &livestream_titles, &oracle, &factory, &token, &config);
+    let result = client.try_initialize(&livestream_ids, &question, &livestream_titles, &oracle, &factory, &token, &config);
+    assert_eq!(result, Err(Ok(MarketError::AlreadyInitialized.into())));
+}
+
+#[test]
+fn test_get_livestream_count() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, _) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64, 3u64]));
+    assert_eq!(client.get_livestream_count(), 3);
+
+    client.add_livestream(&oracle, &4, &String::from_str(&env, "Livestream 4"));
+    assert_eq!(client.get_livestream_count(), 4);
+}
+
+#[test]
+fn test_place_bet_with_referrer() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let referrer = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 1000);
+    mint(&env, &token, &user2, 1000);
+
+    client.place_bet_with_referrer(&user1, &1, &300, &referrer);
+    client.place_bet_with_referrer(&user2, &2, &200, &referrer);
+
+    assert_eq!(client.get_referral_volume(&referrer), 500);
+    assert_eq!(client.get_user_bet(&user1, &1), 300);
+    assert_eq!(client.get_user_bet(&user2, &2), 200);
+    assert_eq!(token::Client::new(&env, &token).balance(&client.address), 500);
+}
+
+#[test]
+fn test_fees_accrue_and_withdraw() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.fee_bps = 1000;
+    let fee_collector = config.fee_collector.clone();
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    let token_client = token::Client::new(&env, &token);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    let user3 = Address::generate(&env);
+    mint(&env, &token, &user1, 100);
+    mint(&env, &token, &user2, 300);
+    mint(&env, &token, &user3, 600);
+
+    client.place_bet(&user1, &1, &100);
+    client.place_bet(&user2, &1, &300);
+    client.place_bet(&user3, &2, &600);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    // Gross payouts are 250 and 750, each charged 10%
+    client.claim_payout(&user1);
+    client.claim_payout(&user2);
+    assert_eq!(token_client.balance(&user1), 225);
+    assert_eq!(token_client.balance(&user2), 675);
+    assert_eq!(client.get_accrued_fees(), 100);
+    assert_eq!(token_client.balance(&fee_collector), 0);
+
+    client.withdraw_fees(&fee_collector);
+    assert_eq!(token_client.balance(&fee_collector), 100);
+    assert_eq!(client.get_accrued_fees(), 0);
+    assert_eq!(token_client.balance(&client.address), 0);
+}
+
+#[test]
+fn test_extend_deadline() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.betting_deadline = 1000;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+
+    env.ledger().set_timestamp(1500);
+    assert_eq!(client.try_place_bet(&user, &1, &100), Err(Ok(MarketError::DeadlinePassed.into())));
+
+    client.extend_deadline(&oracle, &2000);
+    client.place_bet(&user, &1, &100);
+    assert_eq!(client.get_user_bet(&user, &1), 100);
+}
+
+#[test]
+fn test_extend_deadline_rejects_shortening() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.betting_deadline = 1000;
+    let (client, oracle, _) = setup_market_with_config(&env, Vec::from_array(&env, [1u64]), config);
+
+    assert_eq!(client.try_extend_deadline(&oracle, &900), Err(Ok(MarketError::DeadlineNotExtended.into())));
+}
+
+#[test]
+fn test_error_codes() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 1000);
+
+    assert_eq!(client.try_place_bet(&user, &1, &0), Err(Ok(MarketError::InvalidAmount.into())));
+    assert_eq!(client.try_close_market(&user), Err(Ok(MarketError::NotOracle.into())));
+    assert_eq!(client.try_resolve_market(&oracle, &1), Err(Ok(MarketError::NotClosed.into())));
+    assert_eq!(client.try_claim_payout(&user), Err(Ok(MarketError::NotResolved.into())));
+
+    client.place_bet(&user, &1, &100);
+    client.close_market(&oracle);
+    assert_eq!(client.try_place_bet(&user, &1, &100), Err(Ok(MarketError::NotOpen.into())));
+    assert_eq!(client.try_resolve_market(&oracle, &2), Err(Ok(MarketError::NoBetsOnLivestream.into())));
+
+    client.resolve_market(&oracle, &1);
+    let stranger = Address::generate(&env);
+    assert_eq!(client.try_claim_payout(&stranger), Err(Ok(MarketError::NoWinningBet.into())));
+}
+
+#[test]
+fn test_is_betting_open() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.betting_deadline = 1000;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64]), config);
+    assert!(client.is_betting_open());
+
+    // Paused
+    client.pause(&oracle);
+    assert!(!client.is_betting_open());
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    assert_eq!(client.try_place_bet(&user, &1, &100), Err(Ok(MarketError::Paused.into())));
+    client.unpause(&oracle);
+    assert!(client.is_betting_open());
+
+    // Past deadline
+    env.ledger().set_timestamp(1001);
+    assert!(!client.is_betting_open());
+    env.ledger().set_timestamp(1000);
+    assert!(client.is_betting_open());
+
+    // Closed
+    client.close_market(&oracle);
+    assert!(!client.is_betting_open());
+}
+
+#[test]
+fn test_add_bonus_pool() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    let token_client = token::Client::new(&env, &token);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    let user3 = Address::generate(&env);
+    let sponsor = Address::generate(&env);
+    mint(&env, &token, &user1, 100);
+    mint(&env, &token, &user2, 300);
+    mint(&env, &token, &user3, 600);
+    mint(&env, &token, &sponsor, 500);
+
+    client.place_bet(&user1, &1, &100);
+    client.place_bet(&user2, &1, &300);
+    client.place_bet(&user3, &2, &600);
+    client.add_bonus_pool(&sponsor, &500);
+    assert_eq!(client.get_livestream_bets(&1).0, 400);
+
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+    client.claim_payout(&user1);
+    client.claim_payout(&user2);
+
+    // Without the bonus the payouts would be 250 and 750
+    assert_eq!(token_client.balance(&user1), 375);
+    assert_eq!(token_client.balance(&user2), 1125);
+    assert_eq!(token_client.balance(&client.address), 0);
+}
+
+#[test]
+fn test_add_bonus_pool_rejects_settled_market() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let sponsor = Address::generate(&env);
+
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    mint(&env, &token, &sponsor, 500);
+    client.cancel_market(&oracle);
+    assert_eq!(
+        client.try_add_bonus_pool(&sponsor, &500),
+        Err(Ok(ExtMarketError::MarketSettled.into()))
+    );
+
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    mint(&env, &token, &sponsor, 500);
+    client.close_market(&oracle);
+    client.resolve_draw(&oracle);
+    assert_eq!(
+        client.try_add_bonus_pool(&sponsor, &500),
+        Err(Ok(ExtMarketError::MarketSettled.into()))
+    );
+}
+
+#[test]
+fn test_get_role() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64]));
+
+    let user = Address::generate(&env);
+    let spectator = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&user, &1, &100);
+
+    assert_eq!(client.get_role(&spectator), 0);
+    assert_eq!(client.get_role(&user), 1);
+    assert_eq!(client.get_role(&oracle), 2);
+}
+
+#[test]
+fn test_bet_sequence_numbers() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 300);
+
+    for expected_seq in 1..=3u64 {
+        client.place_bet(&user, &1, &100);
+        let data = find_event(&env, "bet_placed").unwrap();
+        let (_, _, _, _, seq) = <(Address, u64, i128, u64, u64)>::try_from_val(&env, &data).unwrap();
+        assert_eq!(seq, expected_seq);
+    }
+    assert_eq!(client.get_bet_count(), 3);
+}
+
+#[test]
+fn test_claim_payout_to() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    let token_client = token::Client::new(&env, &token);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    mint(&env, &token, &user1, 100);
+    mint(&env, &token, &user2, 100);
+    client.place_bet(&user1, &1, &100);
+    client.place_bet(&user2, &2, &100);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    client.claim_payout_to(&user1, &recipient);
+    assert_eq!(token_client.balance(&recipient), 200);
+    assert_eq!(token_client.balance(&user1), 0);
+    assert_eq!(client.get_user_bet(&user1, &1), 0);
+}
+
+#[test]
+fn test_closed_snapshot() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64, 3u64]));
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 1000);
+    mint(&env, &token, &user2, 1000);
+    client.place_bet(&user1, &1, &300);
+    client.place_bet(&user2, &1, &200);
+    client.place_bet(&user2, &2, &400);
+    assert_eq!(client.get_closed_snapshot(&1), 0);
+
+    client.close_market(&oracle);
+    assert_eq!(client.get_closed_snapshot(&1), 500);
+    assert_eq!(client.get_closed_snapshot(&2), 400);
+    assert_eq!(client.get_closed_snapshot(&3), 0);
+}
+
+#[test]
+fn test_multi_oracle_resolution() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let oracle1 = Address::generate(&env);
+    let oracle2 = Address::generate(&env);
+    let oracle3 = Address::generate(&env);
+    let mut config = default_config(&env);
+    config.oracles = Vec::from_array(&env, [oracle1.clone(), oracle2.clone(), oracle3.clone()]);
+    config.threshold = 2;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 100);
+    mint(&env, &token, &user2, 100);
+    client.place_bet(&user1, &1, &100);
+    client.place_bet(&user2, &2, &100);
+    client.close_market(&oracle);
+
+    // The primary oracle can't bypass the oracle set
+    assert_eq!(client.try_resolve_market(&oracle, &1), Err(Ok(MarketError::MultiOracleMarket.into())));
+    assert_eq!(client.try_submit_resolution(&user1, &1), Err(Ok(MarketError::NotOracle.into())));
+
+    // A lone vote and a conflicting vote don't resolve
+    client.submit_resolution(&oracle1, &1);
+    assert_eq!(client.get_market_info().2, State::Closed);
+    client.submit_resolution(&oracle2, &2);
+    assert_eq!(client.get_market_info().2, State::Closed);
+
+    // A second vote agreeing with oracle1 does
+    client.submit_resolution(&oracle3, &1);
+    let (_, _, state, winning_id, _, _, _) = client.get_market_info();
+    assert_eq!(state, State::Resolved);
+    assert_eq!(winning_id, 1);
+
+    client.claim_payout(&user1);
+    assert_eq!(token::Client::new(&env, &token).balance(&user1), 200);
+}
+
+#[test]
+fn test_invalid_threshold() {
+    let env = Env::default();
+    let contract_id = env.register(PredictionMarket, ());
+    let client = PredictionMarketClient::new(&env, &contract_id);
+
+    let mut config = default_config(&env);
+    config.oracles = Vec::from_array(&env, [Address::generate(&env)]);
+    config.threshold = 2;
+    let result = client.try_initialize(
+        &Vec::from_array(&env, [1u64]),
+        &String::from_str(&env, "Which livestream will win?"),
+        &Vec::from_array(&env, [String::from_str(&env, "Livestream 1")]),
+        &Address::generate(&env),
+        &Address::generate(&env),
+        &create_token(&env),
+        &config,
+    );
+    assert_eq!(result, Err(Ok(MarketError::InvalidThreshold.into())));
+}
+
+#[test]
+fn test_platform_and_creator_fees() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.fee_bps = 500;
+    config.creator_fee_bps = 300;
+    let fee_collector = config.fee_collector.clone();
+    let creator = config.creator.clone();
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(client.get_creator(), creator);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 500);
+    mint(&env, &token, &user2, 500);
+    client.place_bet(&user1, &1, &500);
+    client.place_bet(&user2, &2, &500);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    // Gross payout of 1000 minus 5% platform and 3% creator fees
+    client.claim_payout(&user1);
+    assert_eq!(token_client.balance(&user1), 920);
+    assert_eq!(token_client.balance(&creator), 30);
+
+    client.withdraw_fees(&fee_collector);
+    assert_eq!(token_client.balance(&fee_collector), 50);
+}
+
+#[test]
+fn test_combined_fees_capped() {
+    let env = Env::default();
+    let contract_id = env.register(PredictionMarket, ());
+    let client = PredictionMarketClient::new(&env, &contract_id);
+
+    let mut config = default_config(&env);
+    config.fee_bps = 1500;
+    config.creator_fee_bps = 501;
+    let result = client.try_initialize(
+        &Vec::from_array(&env, [1u64]),
+        &String::from_str(&env, "Which livestream will win?"),
+        &Vec::from_array(&env, [String::from_str(&env, "Livestream 1")]),
+        &Address::generate(&env),
+        &Address::generate(&env),
+        &create_token(&env),
+        &config,
+    );
+    assert_eq!(result, Err(Ok(MarketError::FeeTooHigh.into())));
+}
+
+#[test]
+fn test_market_info_winning_pool() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    let user3 = Address::generate(&env);
+    mint(&env, &token, &user1, 300);
+    mint(&env, &token, &user2, 200);
+    mint(&env, &token, &user3, 400);
+    client.place_bet(&user1, &1, &300);
+    client.place_bet(&user2, &1, &200);
+    client.place_bet(&user3, &2, &400);
+    client.close_market(&oracle);
+    assert_eq!(client.get_market_info().6, 0);
+
+    client.resolve_market(&oracle, &1);
+    let (_, _, _, winning_id, total_pool, _, winning_pool) = client.get_market_info();
+    assert_eq!(winning_id, 1);
+    assert_eq!(total_pool, 900);
+    assert_eq!(winning_pool, 500);
+}
+
+#[test]
+fn test_force_close_after_open_timeout() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PredictionMarket, ());
+    let client = PredictionMarketClient::new(&env, &contract_id);
+
+    let oracle = Address::generate(&env);
+    let factory = Address::generate(&env);
+    let mut config = default_config(&env);
+    config.open_timeout = 1000;
+    client.initialize(
+        &Vec::from_array(&env, [1u64, 2u64]),
+        &String::from_str(&env, "Which livestream will win?"),
+        &Vec::from_array(&env, [String::from_str(&env, "Livestream 1"), String::from_str(&env, "Livestream 2")]),
+        &oracle,
+        &factory,
+        &create_token(&env),
+        &config,
+    );
+
+    assert_eq!(client.try_force_close(&factory), Err(Ok(MarketError::OpenTimeoutNotReached.into())));
+
+    env.ledger().with_mut(|li| li.timestamp += 1000);
+    assert_eq!(client.try_force_close(&oracle), Err(Ok(MarketError::NotFactory.into())));
+
+    client.force_close(&factory);
+    assert!(find_event(&env, "market_force_closed").is_some());
+    assert_eq!(client.get_market_info().2, State::Closed);
+}
+
+#[test]
+fn test_user_stake_cap() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.max_user_stake = 500;
+    let (client, _, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 1000);
+    client.place_bet(&user, &1, &200);
+    client.place_bet(&user, &2, &200);
+
+    // The cap covers the user's stake across every livestream
+    assert_eq!(client.try_place_bet(&user, &1, &101), Err(Ok(MarketError::UserStakeCapExceeded.into())));
+    client.place_bet(&user, &1, &100);
+    assert_eq!(client.get_user_bet(&user, &1), 300);
+}
+
+#[test]
+fn test_rescue_tokens() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    // Someone sends an unrelated token straight to the market
+    let stray_token = create_token(&env);
+    mint(&env, &stray_token, &client.address, 250);
+    let owner = Address::generate(&env);
+
+    assert_eq!(
+        client.try_rescue_tokens(&owner, &stray_token, &owner, &250),
+        Err(Ok(MarketError::NotOracle.into()))
+    );
+    client.rescue_tokens(&oracle, &stray_token, &owner, &250);
+    assert!(find_event(&env, "tokens_rescued").is_some());
+    assert_eq!(token::Client::new(&env, &stray_token).balance(&owner), 250);
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&user, &1, &100);
+    assert_eq!(
+        client.try_rescue_tokens(&oracle, &token, &oracle, &100),
+        Err(Ok(MarketError::CannotRescueBettingToken.into()))
+    );
+}
+
+#[test]
+fn test_auto_added_livestream_title_includes_id() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&user, &42, &100);
+
+    let livestream: LivestreamData = env.as_contract(&client.address, || {
+        env.storage().persistent().get(&DataKey::Livestreams(42)).unwrap()
+    });
+    assert_eq!(livestream.title, String::from_str(&env, "Project #42"));
+}
+
+#[test]
+fn test_market_tags() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.tags = Vec::from_array(
+        &env,
+        [
+            String::from_str(&env, "esports"),
+            String::from_str(&env, "finals"),
+            String::from_str(&env, "2024"),
+        ],
+    );
+    let (client, _, _) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    assert_eq!(client.get_tags().len(), 3);
+    assert!(client.has_tag(&String::from_str(&env, "finals")));
+    assert!(!client.has_tag(&String::from_str(&env, "chess")));
+}
+
+#[test]
+fn test_too_many_tags() {
+    let env = Env::default();
+    let contract_id = env.register(PredictionMarket, ());
+    let client = PredictionMarketClient::new(&env, &contract_id);
+
+    let mut config = default_config(&env);
+    for _ in 0..11 {
+        config.tags.push_back(String::from_str(&env, "tag"));
+    }
+    let result = client.try_initialize(
+        &Vec::from_array(&env, [1u64]),
+        &String::from_str(&env, "Which livestream will win?"),
+        &Vec::from_array(&env, [String::from_str(&env, "Livestream 1")]),
+        &Address::generate(&env),
+        &Address::generate(&env),
+        &create_token(&env),
+        &config,
+    );
+    assert_eq!(result, Err(Ok(MarketError::TooManyTags.into())));
+}
+
+#[test]
+fn test_resolve_with_proof() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    assert!(client.get_resolution_proof().is_empty());
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&user, &2, &100);
+    client.close_market(&oracle);
+    let proof = Bytes::from_slice(&env, b"ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi");
+    client.resolve_market_with_proof(&oracle, &2, &proof);
+
+    assert_eq!(client.get_market_info().3, 2);
+    assert_eq!(client.get_resolution_proof(), proof);
+}
+
+#[test]
+fn test_bet_cooldown() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.bet_cooldown = 60;
+    let (client, _, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user = Address::generate(&env);
+    let other = Address::generate(&env);
+    mint(&env, &token, &user, 300);
+    mint(&env, &token, &other, 100);
+    client.place_bet(&user, &1, &100);
+
+    // The cooldown is per user
+    assert_eq!(client.try_place_bet(&user, &2, &100), Err(Ok(MarketError::BetCooldownActive.into())));
+    client.place_bet(&other, &2, &100);
+
+    env.ledger().with_mut(|li| li.timestamp += 60);
+    client.place_bet(&user, &2, &100);
+    assert_eq!(client.get_user_bet(&user, &2), 100);
+}
+
+#[test]
+fn test_place_bet_protected() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let whale = Address::generate(&env);
+    let small = Address::generate(&env);
+    let user = Address::generate(&env);
+    mint(&env, &token, &whale, 900);
+    mint(&env, &token, &small, 100);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&small, &2, &100);
+
+    // The user expected ~2x when the pool looked balanced, but a whale crowded side 1
+    client.place_bet(&whale, &1, &900);
+
+    // Now (1000 + 100) / (900 + 100) = 1.1x
+    assert_eq!(
+        client.try_place_bet_protected(&user, &1, &100, &20_000),
+        Err(Ok(MarketError::SlippageExceeded.into()))
+    );
+    client.place_bet_protected(&user, &1, &100, &11_000);
+    assert_eq!(client.get_user_bet(&user, &1), 100);
+}
+
+#[test]
+fn test_time_remaining_and_age() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().set_timestamp(1_000);
+    let mut config = default_config(&env);
+    config.betting_deadline = 1_500;
+    let (client, _, _) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    assert_eq!(client.get_time_remaining(), 500);
+    assert_eq!(client.get_market_age(), 0);
+
+    env.ledger().set_timestamp(1_200);
+    assert_eq!(client.get_time_remaining(), 300);
+    assert_eq!(client.get_market_age(), 200);
+
+    env.ledger().set_timestamp(1_600);
+    assert_eq!(client.get_time_remaining(), -100);
+    assert_eq!(client.get_market_age(), 600);
+
+    let (no_deadline, _, _) = setup_market(&env, Vec::from_array(&env, [1u64]));
+    assert_eq!(no_deadline.get_time_remaining(), i64::MAX);
+}
+
+#[test]
+fn test_update_livestream_titles() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, _) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64, 3u64]));
+
+    let ids = Vec::from_array(&env, [1u64, 3u64]);
+    let titles = Vec::from_array(&env, [String::from_str(&env, "Team Red"), String::from_str(&env, "Team Blue")]);
+    assert_eq!(
+        client.try_update_livestream_titles(&oracle, &ids, &Vec::new(&env)),
+        Err(Ok(MarketError::MismatchedArrays.into()))
+    );
+    client.update_livestream_titles(&oracle, &ids, &titles);
+    assert!(find_event(&env, "livestreams_updated").is_some());
+
+    let (first, third): (LivestreamData, LivestreamData) = env.as_contract(&client.address, || {
+        (
+            env.storage().persistent().get(&DataKey::Livestreams(1)).unwrap(),
+            env.storage().persistent().get(&DataKey::Livestreams(3)).unwrap(),
+        )
+    });
+    assert_eq!(first.title, String::from_str(&env, "Team Red"));
+    assert_eq!(third.title, String::from_str(&env, "Team Blue"));
+}
+
+#[test]
+fn test_last_claim_clamped_to_balance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    let token_client = token::Client::new(&env, &token);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    let user3 = Address::generate(&env);
+    mint(&env, &token, &user1, 500);
+    mint(&env, &token, &user2, 500);
+    mint(&env, &token, &user3, 1000);
+    client.place_bet(&user1, &1, &500);
+    client.place_bet(&user2, &1, &500);
+    client.place_bet(&user3, &2, &1000);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    // Simulate a small accounting drift: payouts add up to 30 more than the contract holds
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&ExtDataKey::PayoutMultiplier, &2_030_000_000i128);
+    });
+
+    client.claim_payout(&user1);
+    assert_eq!(token_client.balance(&user1), 1015);
+    client.claim_payout(&user2);
+    assert_eq!(token_client.balance(&user2), 985);
+    assert_eq!(token_client.balance(&client.address), 0);
+}
+
+#[test]
+fn test_claim_reverts_on_large_shortfall() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 1000);
+    client.place_bet(&user, &1, &1000);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&ExtDataKey::PayoutMultiplier, &2_000_000_000i128);
+    });
+    assert_eq!(client.try_claim_payout(&user), Err(Ok(MarketError::InsufficientPool.into())));
+}
+
+#[test]
+fn test_removed_livestreams() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, _) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64, 3u64]));
+    assert!(client.get_removed_livestreams().is_empty());
+
+    client.remove_livestream(&oracle, &1);
+    client.remove_livestream(&oracle, &3);
+    assert_eq!(client.get_removed_livestreams(), Vec::from_array(&env, [1u64, 3u64]));
+    assert_eq!(client.get_livestream_count(), 1);
+
+    client.reactivate_livestream(&oracle, &1);
+    assert_eq!(client.get_removed_livestreams(), Vec::from_array(&env, [3u64]));
+    assert!(client.get_livestream(&1).active);
+    assert_eq!(client.get_livestream_count(), 2);
+    assert_eq!(client.try_reactivate_livestream(&oracle, &1), Err(Ok(MarketError::LivestreamActive.into())));
+}
+
+#[test]
+fn test_close_and_resolve() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 100);
+    mint(&env, &token, &user2, 300);
+    client.place_bet(&user1, &1, &100);
+    client.place_bet(&user2, &2, &300);
+
+    client.close_and_resolve(&oracle, &1);
+    let (_, _, state, winning_id, _, _, winning_pool) = client.get_market_info();
+    assert_eq!(state, State::Resolved);
+    assert_eq!(winning_id, 1);
+    assert_eq!(winning_pool, 100);
+
+    client.claim_payout(&user1);
+    assert_eq!(token::Client::new(&env, &token).balance(&user1), 400);
+}
+
+#[test]
+fn test_new_bettor_event_only_on_first_bet() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 200);
+
+    client.place_bet(&user, &1, &100);
+    let data = find_event(&env, "new_bettor").expect("new_bettor not emitted");
+    let (bettor, livestream_id) = <(Address, u64)>::try_from_val(&env, &data).unwrap();
+    assert_eq!(bettor, user);
+    assert_eq!(livestream_id, 1);
+    assert!(find_event(&env, "bet_placed").is_some());
+
+    client.place_bet(&user, &2, &100);
+    assert!(find_event(&env, "new_bettor").is_none());
+    assert!(find_event(&env, "bet_placed").is_some());
+}
+
+#[test]
+fn test_exit_market() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    let token_client = token::Client::new(&env, &token);
+
+    let user = Address::generate(&env);
+    let other = Address::generate(&env);
+    mint(&env, &token, &user, 500);
+    mint(&env, &token, &other, 100);
+    client.place_bet(&user, &1, &200);
+    client.place_bet(&user, &2, &300);
+    client.place_bet(&other, &1, &100);
+    assert_eq!(client.get_market_info().5, 2);
+
+    client.exit_market(&user);
+    assert!(find_event(&env, "user_exited").is_some());
+    assert_eq!(token_client.balance(&user), 500);
+
+    let (_, _, _, _, total_pool, total_bettors, _) = client.get_market_info();
+    assert_eq!(total_pool, 100);
+    assert_eq!(total_bettors, 1);
+    assert_eq!(client.get_livestream_bets(&1).0, 100);
+    assert_eq!(client.get_livestream_bets(&2).0, 0);
+    assert_eq!(client.try_exit_market(&user), Err(Ok(MarketError::NotABettor.into())));
+
+    client.close_market(&oracle);
+    assert_eq!(client.try_exit_market(&other), Err(Ok(MarketError::NotOpen.into())));
+}
+
+#[test]
+fn test_exit_market_after_deadline() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.betting_deadline = 1000;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&user, &1, &100);
+
+    client.pause(&oracle);
+    assert_eq!(client.try_exit_market(&user), Err(Ok(MarketError::Paused.into())));
+    client.unpause(&oracle);
+
+    env.ledger().set_timestamp(1001);
+    assert_eq!(client.try_exit_market(&user), Err(Ok(MarketError::DeadlinePassed.into())));
+    assert_eq!(client.get_user_total_stake(&user), 100);
+}
+
+#[test]
+fn test_require_unique_titles() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PredictionMarket, ());
+    let client = PredictionMarketClient::new(&env, &contract_id);
+
+    let oracle = Address::generate(&env);
+    let mut config = default_config(&env);
+    config.require_unique_titles = true;
+    let result = client.try_initialize(
+        &Vec::from_array(&env, [1u64, 2u64]),
+        &String::from_str(&env, "Which livestream will win?"),
+        &Vec::from_array(&env, [String::from_str(&env, "Team A"), String::from_str(&env, "Team A")]),
+        &oracle,
+        &Address::generate(&env),
+        &create_token(&env),
+        &config,
+    );
+    assert_eq!(result, Err(Ok(MarketError::DuplicateTitle.into())));
+
+    client.initialize(
+        &Vec::from_array(&env, [1u64, 2u64]),
+        &String::from_str(&env, "Which livestream will win?"),
+        &Vec::from_array(&env, [String::from_str(&env, "Team A"), String::from_str(&env, "Team B")]),
+        &oracle,
+        &Address::generate(&env),
+        &create_token(&env),
+        &config,
+    );
+    assert_eq!(
+        client.try_add_livestream(&oracle, &3, &String::from_str(&env, "Team A")),
+        Err(Ok(MarketError::DuplicateTitle.into()))
+    );
+
+    // A removed livestream's title is free to reuse
+    client.remove_livestream(&oracle, &1);
+    client.add_livestream(&oracle, &3, &String::from_str(&env, "Team A"));
+}
+
+#[test]
+fn test_get_fee_config() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.fee_bps = 250;
+    config.creator_fee_bps = 100;
+    let expected = (250u32, config.fee_collector.clone(), 100u32, config.creator.clone());
+    let (client, _, _) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    assert_eq!(client.get_fee_config(), expected);
+
+    // Without fees the bps read as zero; the recipients are still the configured addresses
+    let config = default_config(&env);
+    let expected = (0u32, config.fee_collector.clone(), 0u32, config.creator.clone());
+    let (client, _, _) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    assert_eq!(client.get_fee_config(), expected);
+}
+
+#[test]
+fn test_bettor_pages() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    // One more bettor than fits on a page
+    let mut bettors = Vec::new(&env);
+    for _ in 0..=BETTOR_PAGE_SIZE {
+        let user = Address::generate(&env);
+        mint(&env, &token, &user, 10);
+        client.place_bet(&user, &1, &10);
+        bettors.push_back(user);
+    }
+
+    let mut listed = client.get_bettors(&0, &30);
+    listed.append(&client.get_bettors(&30, &30));
+    assert_eq!(listed, bettors);
+    assert!(client.get_bettors(&(BETTOR_PAGE_SIZE + 1), &10).is_empty());
+
+    // Exiting moves the last bettor into the freed slot
+    let first = bettors.get(0).unwrap();
+    let last = bettors.get(BETTOR_PAGE_SIZE).unwrap();
+    client.exit_market(&first);
+    let listed = client.get_bettors(&0, &100);
+    assert_eq!(listed.len(), BETTOR_PAGE_SIZE);
+    assert_eq!(listed.get(0).unwrap(), last);
+}
+
+#[test]
+fn test_resolve_weighted() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    let token_client = token::Client::new(&env, &token);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 400);
+    mint(&env, &token, &user2, 400);
+    client.place_bet(&user1, &1, &400);
+    client.place_bet(&user2, &2, &400);
+    client.close_market(&oracle);
+
+    let ids = Vec::from_array(&env, [1u64, 2u64]);
+    assert_eq!(
+        client.try_resolve_weighted(&oracle, &ids, &Vec::from_array(&env, [3u64])),
+        Err(Ok(MarketError::MismatchedArrays.into()))
+    );
+    client.resolve_weighted(&oracle, &ids, &Vec::from_array(&env, [3u64, 1u64]));
+    assert_eq!(client.get_market_info().3, 1);
+
+    // Weighted stakes are 1200 and 400, so the 800 pool splits 600 / 200
+    client.claim_payout(&user1);
+    client.claim_payout(&user2);
+    assert_eq!(token_client.balance(&user1), 600);
+    assert_eq!(token_client.balance(&user2), 200);
+    assert_eq!(client.try_claim_payout(&user1), Err(Ok(MarketError::NoWinningBet.into())));
+}
+
+#[test]
+fn test_sweep_unclaimed_after_claim_deadline() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.claim_window = 1000;
+    let fee_collector = config.fee_collector.clone();
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    let token_client = token::Client::new(&env, &token);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 300);
+    mint(&env, &token, &user2, 200);
+    client.place_bet(&user1, &1, &300);
+    client.place_bet(&user2, &2, &200);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    assert_eq!(client.try_sweep_unclaimed(&oracle), Err(Ok(MarketError::ClaimWindowOpen.into())));
+
+    env.ledger().with_mut(|li| li.timestamp += 1001);
+    client.sweep_unclaimed(&oracle);
+    assert!(find_event(&env, "unclaimed_swept").is_some());
+    assert_eq!(token_client.balance(&fee_collector), 500);
+
+    assert_eq!(client.try_claim_payout(&user1), Err(Ok(MarketError::ClaimDeadlinePassed.into())));
+    assert_eq!(client.try_sweep_unclaimed(&oracle), Err(Ok(MarketError::NothingToSweep.into())));
+}
+
+#[test]
+fn test_get_livestream_bettors() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    let user3 = Address::generate(&env);
+    let other = Address::generate(&env);
+    for user in [&user1, &user2, &user3, &other] {
+        mint(&env, &token, user, 500);
+    }
+    client.place_bet(&user1, &1, &100);
+    client.place_bet(&other, &2, &100);
+    client.place_bet(&user2, &1, &200);
+    client.place_bet(&user3, &1, &300);
+    client.place_bet(&user1, &1, &50);
+
+    let expected = Vec::from_array(&env, [(user1.clone(), 150i128), (user2.clone(), 200i128), (user3.clone(), 300i128)]);
+    assert_eq!(client.get_livestream_bettors(&1, &0, &10), expected);
+    assert_eq!(
+        client.get_livestream_bettors(&1, &0, &2),
+        Vec::from_array(&env, [(user1, 150i128)])
+    );
+    assert_eq!(client.get_livestream_bettors(&2, &0, &10), Vec::from_array(&env, [(other, 100i128)]));
+}
+
+#[contract]
+struct MockDataFeed;
+
+#[contractimpl]
+impl MockDataFeed {
+    pub fn get_winner(_env: Env, _market: Address) -> u64 {
+        2
+    }
+}
+
+#[test]
+fn test_auto_resolve_from_data_feed() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.oracle_contract = Some(env.register(MockDataFeed, ()));
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 100);
+    mint(&env, &token, &user2, 100);
+    client.place_bet(&user1, &1, &100);
+    client.place_bet(&user2, &2, &100);
+
+    assert_eq!(client.try_auto_resolve(), Err(Ok(MarketError::NotClosed.into())));
+    client.close_market(&oracle);
+    client.auto_resolve();
+
+    let (_, _, state, winning_id, _, _, _) = client.get_market_info();
+    assert_eq!(state, State::Resolved);
+    assert_eq!(winning_id, 2);
+    client.claim_payout(&user2);
+    assert_eq!(token::Client::new(&env, &token).balance(&user2), 200);
+}
+
+#[test]
+fn test_auto_resolve_requires_oracle_contract() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, _) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    client.close_market(&oracle);
+    assert_eq!(client.try_auto_resolve(), Err(Ok(MarketError::NoOracleContract.into())));
+}
+
+#[test]
+fn test_admin_separate_from_oracle() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let mut config = default_config(&env);
+    config.admin = Some(admin.clone());
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    assert_eq!(client.get_admin(), admin);
+
+    // The admin shapes the market, the oracle can't
+    let title = String::from_str(&env, "Livestream 3");
+    assert_eq!(client.try_add_livestream(&oracle, &3, &title), Err(Ok(MarketError::NotAdmin.into())));
+    client.add_livestream(&admin, &3, &title);
+    assert_eq!(client.try_pause(&oracle), Err(Ok(MarketError::NotAdmin.into())));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&user, &3, &100);
+
+    // The oracle resolves, the admin can't
+    assert_eq!(client.try_close_market(&admin), Err(Ok(MarketError::NotOracle.into())));
+    client.close_market(&oracle);
+    assert_eq!(client.try_resolve_market(&admin, &3), Err(Ok(MarketError::NotOracle.into())));
+    client.resolve_market(&oracle, &3);
+}
+
+#[test]
+fn test_min_pool_requires_cancel() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.min_pool = 1000;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    let token_client = token::Client::new(&env, &token);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 30);
+    mint(&env, &token, &user2, 20);
+    client.place_bet(&user1, &1, &30);
+    client.place_bet(&user2, &2, &20);
+    client.close_market(&oracle);
+
+    assert_eq!(client.try_resolve_market(&oracle, &1), Err(Ok(MarketError::PoolBelowMinimum.into())));
+    assert_eq!(client.try_claim_refund(&user1), Err(Ok(MarketError::NotCancelled.into())));
+
+    client.cancel_market(&oracle);
+    assert_eq!(client.get_market_info().2, State::Cancelled);
+    client.claim_refund(&user1);
+    client.claim_refund(&user2);
+    assert_eq!(token_client.balance(&user1), 30);
+    assert_eq!(token_client.balance(&user2), 20);
+    assert_eq!(client.try_claim_refund(&user1), Err(Ok(MarketError::NotABettor.into())));
+    assert_eq!(client.try_cancel_market(&oracle), Err(Ok(MarketError::CannotCancel.into())));
+}
+
+#[test]
+fn test_version_tracks_mutations() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+
+    let mut version = client.get_version();
+    let mut assert_bumped = |client: &PredictionMarketClient| {
+        let current = client.get_version();
+        assert!(current > version);
+        version = current;
+    };
+
+    client.place_bet(&user, &1, &100);
+    assert_bumped(&client);
+    client.add_livestream(&oracle, &3, &String::from_str(&env, "Livestream 3"));
+    assert_bumped(&client);
+    client.remove_livestream(&oracle, &3);
+    assert_bumped(&client);
+
+    // Views leave it alone
+    let before = client.get_version();
+    client.get_market_info();
+    client.get_livestream_bets(&1);
+    assert_eq!(client.get_version(), before);
+
+    client.enable_auto_claim(&user);
+    assert_bumped(&client);
+    client.close_market(&oracle);
+    assert_bumped(&client);
+    client.resolve_market(&oracle, &1);
+    assert_bumped(&client);
+}
+
+#[test]
+fn test_version_bumped_by_cancel_and_rescue() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, _) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    client.close_market(&oracle);
+    let closed = client.get_version();
+    client.cancel_market(&oracle);
+    assert!(client.get_version() > closed);
+
+    let stray = create_token(&env);
+    mint(&env, &stray, &client.address, 10);
+    let cancelled = client.get_version();
+    client.rescue_tokens(&oracle, &stray, &oracle, &10);
+    assert!(client.get_version() > cancelled);
+}
+
+#[test]
+fn test_claim_payout_in_payout_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let payout_token = create_token(&env);
+    let mut config = default_config(&env);
+    config.payout_token = Some(payout_token.clone());
+    config.payout_rate_bps = 5000;
+    let fee_collector = config.fee_collector.clone();
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    assert_eq!(client.get_payout_token(), Some(payout_token.clone()));
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 1000);
+    mint(&env, &token, &user2, 1000);
+    client.place_bet(&user1, &1, &1000);
+    client.place_bet(&user2, &2, &1000);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    // Without collateral the converted payout can't be covered
+    assert_eq!(client.try_claim_payout(&user1), Err(Ok(MarketError::InsufficientPool.into())));
+
+    mint(&env, &payout_token, &client.address, 1000);
+    client.claim_payout(&user1);
+    assert_eq!(token::Client::new(&env, &payout_token).balance(&user1), 1000);
+    assert_eq!(token::Client::new(&env, &token).balance(&user1), 0);
+
+    // The replaced betting tokens are released to the fee collector, not counted as fees
+    assert_eq!(client.get_accrued_fees(), 0);
+    assert_eq!(client.try_withdraw_fees(&fee_collector), Err(Ok(MarketError::NoFees.into())));
+    client.withdraw_released_collateral(&fee_collector);
+    assert_eq!(token::Client::new(&env, &token).balance(&fee_collector), 2000);
+    assert_eq!(
+        client.try_withdraw_released_collateral(&fee_collector),
+        Err(Ok(ExtMarketError::NoReleasedCollateral.into()))
+    );
+}
+
+#[test]
+fn test_payout_token_shortfall_uses_payout_balance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let payout_token = create_token(&env);
+    let mut config = default_config(&env);
+    config.payout_token = Some(payout_token.clone());
+    config.payout_rate_bps = 10_000;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 1000);
+    mint(&env, &token, &user2, 1000);
+    client.place_bet(&user1, &1, &1000);
+    client.place_bet(&user2, &2, &1000);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    // Plenty of betting tokens, but the collateral is a rounding amount short
+    mint(&env, &payout_token, &client.address, 1990);
+    client.claim_payout(&user1);
+    assert_eq!(token::Client::new(&env, &payout_token).balance(&user1), 1990);
+}
+
+#[test]
+fn test_solvency_with_payout_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let payout_token = create_token(&env);
+    let mut config = default_config(&env);
+    config.payout_token = Some(payout_token.clone());
+    config.payout_rate_bps = 20_000;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    mint(&env, &payout_token, &client.address, 1000);
+
+    let winner = Address::generate(&env);
+    let loser = Address::generate(&env);
+    mint(&env, &token, &winner, 500);
+    mint(&env, &token, &loser, 500);
+    client.place_bet(&winner, &1, &500);
+    assert_eq!(client.get_max_liability(), 1000);
+    assert!(client.is_solvent());
+
+    // A large bet pushes the converted liability past the collateral
+    client.place_bet(&loser, &2, &500);
+    assert_eq!(client.get_max_liability(), 2000);
+    assert!(!client.is_solvent());
+
+    mint(&env, &payout_token, &client.address, 1000);
+    assert!(client.is_solvent());
+
+    // Resolution doesn't change what's owed; the last claim clears it
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+    assert_eq!(client.get_max_liability(), 2000);
+    client.claim_payout(&winner);
+    assert_eq!(client.get_max_liability(), 0);
+    assert!(client.is_solvent());
+}
+
+#[test]
+fn test_rescue_payout_token_with_claims_outstanding() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let payout_token = create_token(&env);
+    let mut config = default_config(&env);
+    config.payout_token = Some(payout_token.clone());
+    config.payout_rate_bps = 10_000;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    mint(&env, &payout_token, &client.address, 1000);
+
+    let winner = Address::generate(&env);
+    mint(&env, &token, &winner, 500);
+    client.place_bet(&winner, &1, &500);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    assert_eq!(
+        client.try_rescue_tokens(&oracle, &payout_token, &oracle, &500),
+        Err(Ok(ExtMarketError::ClaimsOutstanding.into()))
+    );
+
+    // Once the winner has claimed, the leftover collateral can be recovered
+    client.claim_payout(&winner);
+    client.rescue_tokens(&oracle, &payout_token, &oracle, &500);
+    assert_eq!(token::Client::new(&env, &payout_token).balance(&oracle), 500);
+}
+
+#[test]
+fn test_solvency_in_betting_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let winner1 = Address::generate(&env);
+    let winner2 = Address::generate(&env);
+    let loser = Address::generate(&env);
+    for user in [&winner1, &winner2, &loser] {
+        mint(&env, &token, user, 300);
+    }
+    client.place_bet(&winner1, &1, &100);
+    client.place_bet(&winner2, &1, &100);
+    client.place_bet(&loser, &2, &200);
+    assert_eq!(client.get_max_liability(), 400);
+    assert!(client.is_solvent());
+
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+    client.claim_payout(&winner1);
+    assert_eq!(client.get_max_liability(), 200);
+    assert!(client.is_solvent());
+
+    let (client, oracle, _) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    client.cancel_market(&oracle);
+    assert_eq!(client.get_max_liability(), 0);
+}
+
+#[test]
+fn test_transfer_position() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    mint(&env, &token, &seller, 100);
+    client.place_bet(&seller, &1, &100);
+
+    assert_eq!(
+        client.try_transfer_position(&seller, &buyer, &1, &101),
+        Err(Ok(MarketError::InvalidAmount.into()))
+    );
+
+    client.transfer_position(&seller, &buyer, &1, &50);
+    assert_eq!(client.get_user_bet(&seller, &1), 50);
+    assert_eq!(client.get_user_bet(&buyer, &1), 50);
+    assert_eq!(client.get_livestream_bets(&1).0, 100);
+    let (_, _, _, _, total_pool, total_bettors, _) = client.get_market_info();
+    assert_eq!((total_pool, total_bettors), (100, 2));
+    assert_eq!(client.get_bettors(&0, &10), Vec::from_array(&env, [seller.clone(), buyer.clone()]));
+
+    client.close_market(&oracle);
+    assert_eq!(
+        client.try_transfer_position(&seller, &buyer, &1, &50),
+        Err(Ok(MarketError::NotOpen.into()))
+    );
+}
+
+#[test]
+fn test_transfer_position_after_deadline() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.betting_deadline = 1000;
+    let (client, _, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    mint(&env, &token, &seller, 100);
+    client.place_bet(&seller, &1, &100);
+
+    env.ledger().set_timestamp(1001);
+    assert_eq!(
+        client.try_transfer_position(&seller, &buyer, &1, &50),
+        Err(Ok(MarketError::DeadlinePassed.into()))
+    );
+}
+
+#[test]
+fn test_transfer_position_while_paused_or_frozen() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    mint(&env, &token, &seller, 200);
+    client.place_bet(&seller, &1, &100);
+    client.place_bet(&seller, &2, &100);
+
+    client.pause(&oracle);
+    assert_eq!(
+        client.try_transfer_position(&seller, &buyer, &1, &50),
+        Err(Ok(MarketError::Paused.into()))
+    );
+    client.unpause(&oracle);
+
+    client.set_livestream_betting(&oracle, &1, &false);
+    assert_eq!(
+        client.try_transfer_position(&seller, &buyer, &1, &50),
+        Err(Ok(ExtMarketError::LivestreamFrozen.into()))
+    );
+    client.transfer_position(&seller, &buyer, &2, &50);
+    assert_eq!(client.get_user_bet(&buyer, &2), 50);
+}
+
+#[test]
+fn test_transfer_position_respects_recipient_stake_cap() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.max_user_stake = 150;
+    let (client, _, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    mint(&env, &token, &seller, 150);
+    mint(&env, &token, &buyer, 100);
+    client.place_bet(&seller, &1, &150);
+    client.place_bet(&buyer, &2, &100);
+
+    assert_eq!(
+        client.try_transfer_position(&seller, &buyer, &1, &60),
+        Err(Ok(MarketError::UserStakeCapExceeded.into()))
+    );
+    client.transfer_position(&seller, &buyer, &1, &50);
+    assert_eq!(client.get_user_total_stake(&buyer), 150);
+}
+
+#[test]
+fn test_fee_exempt_claim() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.fee_bps = 1000;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    let token_client = token::Client::new(&env, &token);
+
+    let vip = Address::generate(&env);
+    let regular = Address::generate(&env);
+    let loser = Address::generate(&env);
+    for user in [&vip, &regular, &loser] {
+        mint(&env, &token, user, 100);
+    }
+    client.place_bet(&vip, &1, &100);
+    client.place_bet(&regular, &1, &100);
+    client.place_bet(&loser, &2, &100);
+
+    // The admin defaults to the oracle
+    client.set_fee_exempt(&oracle, &vip, &true);
+    assert!(client.is_fee_exempt(&vip));
+    assert!(!client.is_fee_exempt(&regular));
+
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+    client.claim_payout(&vip);
+    client.claim_payout(&regular);
+
+    assert_eq!(token_client.balance(&vip), 150);
+    assert_eq!(token_client.balance(&regular), 135);
+    assert_eq!(client.get_accrued_fees(), 15);
+}
+
+#[test]
+fn test_resolve_draw_refunds_stakes() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.draw_fee_bps = 100;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    let token_client = token::Client::new(&env, &token);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 1000);
+    mint(&env, &token, &user2, 500);
+    client.place_bet(&user1, &1, &1000);
+    client.place_bet(&user2, &2, &500);
+
+    assert_eq!(client.try_resolve_draw(&oracle), Err(Ok(MarketError::NotClosed.into())));
+    client.close_market(&oracle);
+    assert_eq!(client.try_resolve_draw(&user1), Err(Ok(MarketError::NotOracle.into())));
+    client.resolve_draw(&oracle);
+    assert!(find_event(&env, "market_draw").is_some());
+    assert_eq!(client.get_market_info().2, State::Draw);
+
+    // Each bettor gets their stake back less the 1% processing fee
+    client.claim_refund(&user1);
+    client.claim_refund(&user2);
+    assert_eq!(token_client.balance(&user1), 990);
+    assert_eq!(token_client.balance(&user2), 495);
+    assert_eq!(client.get_accrued_fees(), 15);
+    assert_eq!(client.try_claim_refund(&user1), Err(Ok(MarketError::NotABettor.into())));
+}
+
+#[test]
+fn test_get_resolution_records_resolver() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&user, &2, &100);
+    client.close_market(&oracle);
+    assert_eq!(client.try_get_resolution(), Err(Ok(MarketError::NotResolved.into())));
+
+    env.ledger().set_timestamp(5000);
+    client.resolve_market(&oracle, &2);
+    assert_eq!(client.get_resolution(), (2, 5000, oracle));
+}
+
+#[test]
+fn test_set_group() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, _) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64, 3u64]));
+    let user = Address::generate(&env);
+
+    client.set_group(&oracle, &1, &7);
+    client.set_group(&oracle, &3, &7);
+    client.set_group(&oracle, &2, &8);
+    assert_eq!(client.get_group(&1), 7);
+    assert_eq!(client.get_group(&2), 8);
+
+    client.set_group(&oracle, &2, &0);
+    assert_eq!(client.get_group(&2), 0);
+    assert_eq!(client.try_set_group(&user, &1, &9), Err(Ok(MarketError::NotAdmin.into())));
+}
+
+#[test]
+fn test_min_bettors_to_resolve() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.min_bettors = 2;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    mint(&env, &token, &user1, 200);
+    mint(&env, &token, &user2, 100);
+    client.place_bet(&user1, &1, &100);
+    client.place_bet(&user1, &2, &100);
+    client.close_market(&oracle);
+    assert_eq!(
+        client.try_resolve_market(&oracle, &1),
+        Err(Ok(ExtMarketError::NotEnoughBettors.into()))
+    );
+
+    let mut config = default_config(&env);
+    config.min_bettors = 2;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    mint(&env, &token, &user1, 100);
+    mint(&env, &token, &user2, 100);
+    client.place_bet(&user1, &1, &100);
+    client.place_bet(&user2, &2, &100);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+    assert_eq!(client.get_market_info().2, State::Resolved);
+}
+
+#[test]
+fn test_freeze_livestream_betting() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 200);
+
+    assert_eq!(
+        client.try_set_livestream_betting(&user, &1, &false),
+        Err(Ok(MarketError::NotAdmin.into()))
+    );
+    client.set_livestream_betting(&oracle, &1, &false);
+    assert!(!client.is_livestream_betting_enabled(&1));
+    assert!(client.is_livestream_betting_enabled(&2));
+
+    assert_eq!(client.try_place_bet(&user, &1, &100), Err(Ok(ExtMarketError::LivestreamFrozen.into())));
+    client.place_bet(&user, &2, &100);
+
+    client.set_livestream_betting(&oracle, &1, &true);
+    client.place_bet(&user, &1, &100);
+    assert_eq!(client.get_user_bet(&user, &1), 100);
+}
+
+#[test]
+fn test_refund_removed_livestream_at_resolution() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    let token_client = token::Client::new(&env, &token);
+
+    let stranded = Address::generate(&env);
+    let winner = Address::generate(&env);
+    mint(&env, &token, &stranded, 300);
+    mint(&env, &token, &winner, 100);
+    client.place_bet(&stranded, &1, &300);
+    client.place_bet(&winner, &2, &100);
+
+    client.remove_livestream(&oracle, &1);
+    client.close_market(&oracle);
+    assert_eq!(client.try_claim_refund(&stranded), Err(Ok(MarketError::NotCancelled.into())));
+    client.resolve_market(&oracle, &2);
+
+    // The removed livestream's stake is returned rather than paid to the winner
+    client.claim_refund(&stranded);
+    client.claim_payout(&winner);
+    assert_eq!(token_client.balance(&stranded), 300);
+    assert_eq!(token_client.balance(&winner), 100);
+    assert_eq!(client.try_claim_refund(&winner), Err(Ok(MarketError::NotABettor.into())));
+}
+
+#[test]
+fn test_removed_livestream_refund_after_sweep() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.claim_window = 1000;
+    let fee_collector = config.fee_collector.clone();
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+    let token_client = token::Client::new(&env, &token);
+
+    let stranded = Address::generate(&env);
+    let winner = Address::generate(&env);
+    mint(&env, &token, &stranded, 300);
+    mint(&env, &token, &winner, 100);
+    client.place_bet(&stranded, &1, &300);
+    client.place_bet(&winner, &2, &100);
+    client.remove_livestream(&oracle, &1);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &2);
+    client.claim_payout(&winner);
+
+    // The unclaimed refund expires with the claim window and is swept
+    env.ledger().with_mut(|li| li.timestamp += 1001);
+    client.sweep_unclaimed(&oracle);
+    assert_eq!(token_client.balance(&fee_collector), 300);
+    assert_eq!(
+        client.try_claim_refund(&stranded),
+        Err(Ok(MarketError::ClaimDeadlinePassed.into()))
+    );
+}
+
+#[test]
+fn test_total_fees_collected_survives_withdrawal() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.fee_bps = 500;
+    config.creator_fee_bps = 500;
+    let fee_collector = config.fee_collector.clone();
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let winner1 = Address::generate(&env);
+    let winner2 = Address::generate(&env);
+    let loser = Address::generate(&env);
+    for user in [&winner1, &winner2, &loser] {
+        mint(&env, &token, user, 200);
+    }
+    client.place_bet(&winner1, &1, &100);
+    client.place_bet(&winner2, &1, &100);
+    client.place_bet(&loser, &2, &200);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    // Each winner grosses 200 and pays 10 + 10 in fees
+    client.claim_payout(&winner1);
+    client.withdraw_fees(&fee_collector);
+    client.claim_payout(&winner2);
+    assert_eq!(client.get_accrued_fees(), 10);
+    assert_eq!(client.get_total_fees_collected(), 40);
+}
+
+#[test]
+fn test_bet_events_can_be_disabled() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.emit_bet_events = false;
+    let (client, _, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&user, &1, &100);
+    assert!(find_event(&env, "bet_placed").is_none());
+    assert_eq!(client.get_user_bet(&user, &1), 100);
+}
+
+#[test]
+fn test_reopen_market_within_window() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().set_timestamp(1000);
+    let mut config = default_config(&env);
+    config.reopen_window = 600;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 200);
+    client.place_bet(&user, &1, &100);
+    client.close_market(&oracle);
+
+    env.ledger().set_timestamp(1600);
+    client.reopen_market(&oracle);
+    assert!(find_event(&env, "market_reopened").is_some());
+    assert!(client.is_betting_open());
+    client.place_bet(&user, &2, &100);
+
+    // The window runs from the latest close
+    client.close_market(&oracle);
+    env.ledger().set_timestamp(2201);
+    assert_eq!(client.try_reopen_market(&oracle), Err(Ok(ExtMarketError::ReopenWindowClosed.into())));
+}
+
+#[test]
+fn test_reopen_resolved_market_fails() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.reopen_window = 600;
+    let (client, oracle, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&user, &1, &100);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    assert_eq!(client.try_reopen_market(&oracle), Err(Ok(MarketError::NotClosed.into())));
+}
+
+#[test]
+fn test_claim_stats() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let winner1 = Address::generate(&env);
+    let winner2 = Address::generate(&env);
+    let winner3 = Address::generate(&env);
+    let loser = Address::generate(&env);
+    for user in [&winner1, &winner2, &winner3, &loser] {
+        mint(&env, &token, user, 200);
+    }
+    client.place_bet(&winner1, &1, &100);
+    client.place_bet(&winner1, &1, &100);
+    client.place_bet(&winner2, &1, &100);
+    client.place_bet(&winner3, &2, &100);
+    client.transfer_position(&winner3, &loser, &2, &100);
+    client.place_bet(&winner3, &1, &100);
+    client.place_bet(&loser, &2, &100);
+
+    client.close_market(&oracle);
+    assert_eq!(client.get_claim_stats(), (0, 0));
+    client.resolve_market(&oracle, &1);
+    assert_eq!(client.get_claim_stats(), (0, 3));
+
+    client.claim_payout(&winner1);
+    assert_eq!(client.get_claim_stats(), (1, 3));
+    client.claim_payout(&winner3);
+    assert_eq!(client.get_claim_stats(), (2, 3));
+}
+
+#[test]
+fn test_max_concentration() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.max_concentration_bps = 8000;
+    let (client, _, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    mint(&env, &token, &alice, 1000);
+    mint(&env, &token, &bob, 1000);
+
+    // The first stake is exempt since nothing else is in the pool yet
+    client.place_bet(&alice, &1, &100);
+    client.place_bet(&bob, &2, &100);
+
+    // 400 of 500 is exactly the cap
+    client.place_bet(&alice, &1, &300);
+    assert_eq!(
+        client.try_place_bet(&alice, &1, &1),
+        Err(Ok(ExtMarketError::ConcentrationExceeded.into()))
+    );
+    client.place_bet(&bob, &2, &10);
+    client.place_bet(&alice, &1, &1);
+    assert_eq!(client.get_user_bet(&alice, &1), 401);
+}
+
+#[test]
+fn test_max_concentration_ignores_bonus() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.max_concentration_bps = 8000;
+    let (client, _, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let sponsor = Address::generate(&env);
+    mint(&env, &token, &alice, 1000);
+    mint(&env, &token, &bob, 1000);
+    mint(&env, &token, &sponsor, 1000);
+
+    client.place_bet(&bob, &2, &100);
+    client.add_bonus_pool(&sponsor, &1000);
+
+    // Counting the bonus, 401 of 1501 would be well under the cap
+    client.place_bet(&alice, &1, &400);
+    assert_eq!(
+        client.try_place_bet(&alice, &1, &1),
+        Err(Ok(ExtMarketError::ConcentrationExceeded.into()))
+    );
+}
+
+#[test]
+fn test_get_token_decimals() {
+    let env = Env::default();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [1u64]));
+
+    // Stellar asset contracts use 7 decimals
+    assert_eq!(token::Client::new(&env, &token).decimals(), 7);
+    assert_eq!(client.get_token_decimals(), 7);
+}
+
+#[test]
+fn test_resolve_to_removed_livestream_fails() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 200);
+    client.place_bet(&user, &1, &100);
+    client.place_bet(&user, &2, &100);
+    client.remove_livestream(&oracle, &1);
+    client.close_market(&oracle);
+
+    assert_eq!(
+        client.try_resolve_market(&oracle, &1),
+        Err(Ok(ExtMarketError::WinnerNotInMarket.into()))
+    );
+    client.resolve_market(&oracle, &2);
+}
+
+#[test]
+fn test_resolve_weighted_rejects_removed_livestream() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64, 3u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 300);
+    client.place_bet(&user, &1, &100);
+    client.place_bet(&user, &2, &100);
+    client.place_bet(&user, &3, &100);
+    client.remove_livestream(&oracle, &3);
+    client.close_market(&oracle);
+
+    assert_eq!(
+        client.try_resolve_weighted(&oracle, &Vec::from_array(&env, [1u64, 3u64]), &Vec::from_array(&env, [1u64, 1u64])),
+        Err(Ok(ExtMarketError::WinnerNotInMarket.into()))
+    );
+    client.resolve_weighted(&oracle, &Vec::from_array(&env, [1u64, 2u64]), &Vec::from_array(&env, [1u64, 1u64]));
+}
+
+#[test]
+fn test_claims_use_frozen_payout_multiplier() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let winner = Address::generate(&env);
+    let loser = Address::generate(&env);
+    mint(&env, &token, &winner, 400);
+    mint(&env, &token, &loser, 200);
+    client.place_bet(&winner, &1, &400);
+    client.place_bet(&loser, &2, &200);
+    client.close_market(&oracle);
+    assert_eq!(client.get_payout_multiplier(), 0);
+    client.resolve_market(&oracle, &1);
+    assert_eq!(client.get_payout_multiplier(), 1_500_000_000);
+
+    // Pool changes after resolution don't move the payout
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&DataKey::TotalPool, &10_000i128);
+    });
+    client.claim_payout(&winner);
+    assert_eq!(token::Client::new(&env, &token).balance(&winner), 600);
+}
+
+#[test]
+fn test_process_auto_claims() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    let token_client = token::Client::new(&env, &token);
+
+    let auto = Address::generate(&env);
+    let manual = Address::generate(&env);
+    let loser = Address::generate(&env);
+    for user in [&auto, &manual, &loser] {
+        mint(&env, &token, user, 100);
+    }
+    client.place_bet(&auto, &1, &100);
+    client.place_bet(&manual, &1, &100);
+    client.place_bet(&loser, &2, &100);
+    client.enable_auto_claim(&auto);
+    client.enable_auto_claim(&loser);
+
+    client.close_market(&oracle);
+    assert_eq!(client.try_enable_auto_claim(&manual), Err(Ok(MarketError::NotOpen.into())));
+    client.resolve_market(&oracle, &1);
+
+    // Processed in two pages; only the opted-in winner is paid
+    assert_eq!(client.process_auto_claims(&0, &2), 1);
+    assert_eq!(client.process_auto_claims(&2, &2), 0);
+    assert_eq!(token_client.balance(&auto), 150);
+    assert_eq!(token_client.balance(&manual), 0);
+    assert_eq!(client.process_auto_claims(&0, &3), 0);
+
+    client.claim_payout(&manual);
+    assert_eq!(token_client.balance(&manual), 150);
+}
+
+#[test]
+fn test_get_user_total_stake() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64, 3u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 500);
+    assert_eq!(client.get_user_total_stake(&user), 0);
+
+    client.place_bet(&user, &1, &150);
+    client.place_bet(&user, &3, &250);
+    assert_eq!(client.get_user_total_stake(&user), 400);
+}
+
+#[test]
+fn test_bet_placed_topics_include_livestream() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [5u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&user, &5, &100);
+
+    let expected = String::from_str(&env, "bet_placed");
+    let (_, topics, _) = env.events().all().iter()
+        .find(|(_, topics, _)| String::try_from_val(&env, &topics.get(0).unwrap()) == Ok(expected.clone()))
+        .unwrap();
+    assert_eq!(topics.len(), 2);
+    assert_eq!(u64::try_from_val(&env, &topics.get(1).unwrap()), Ok(5));
+}
+
+#[test]
+fn test_get_state_label() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let label = |s: &str| String::from_str(&env, s);
+
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 100);
+    client.place_bet(&user, &1, &100);
+    assert_eq!(client.get_state_label(), label("open"));
+    client.close_market(&oracle);
+    assert_eq!(client.get_state_label(), label("closed"));
+    client.resolve_market(&oracle, &1);
+    assert_eq!(client.get_state_label(), label("resolved"));
+
+    let (client, oracle, _) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    client.cancel_market(&oracle);
+    assert_eq!(client.get_state_label(), label("cancelled"));
+
+    let (client, oracle, _) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    client.close_market(&oracle);
+    client.resolve_draw(&oracle);
+    assert_eq!(client.get_state_label(), label("draw"));
+}
+
+#[test]
+fn test_bet_tick() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.bet_tick = 100;
+    let (client, _, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 1000);
+    assert_eq!(
+        client.try_place_bet(&user, &1, &150),
+        Err(Ok(ExtMarketError::AmountNotMultipleOfTick.into()))
+    );
+    client.place_bet(&user, &1, &200);
+    assert_eq!(client.get_user_bet(&user, &1), 200);
+}
+
+#[test]
+fn test_get_break_even() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    mint(&env, &token, &alice, 1000);
+    mint(&env, &token, &bob, 1000);
+    client.place_bet(&alice, &1, &100);
+    client.place_bet(&bob, &2, &300);
+
+    // 100 more on livestream 1 pays 500 / 200 = 2.5x, breaking even at 40%
+    assert_eq!(client.get_break_even(&1, &100), 4000);
+    // 100 more on livestream 2 pays 500 / 400 = 1.25x, breaking even at 80%
+    assert_eq!(client.get_break_even(&2, &100), 8000);
+    // Fractional thresholds round up: 150 / 450
+    assert_eq!(client.get_break_even(&1, &50), 3334);
+    // An empty market always pays back exactly the stake
+    let (empty, _, _) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+    assert_eq!(empty.get_break_even(&1, &50), 10_000);
+
+    assert_eq!(
+        client.try_get_break_even(&1, &0),
+        Err(Ok(MarketError::InvalidAmount.into()))
+    );
+}
+
+#[test]
+fn test_cleanup_after_settlement() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let winner = Address::generate(&env);
+    let loser = Address::generate(&env);
+    mint(&env, &token, &winner, 300);
+    mint(&env, &token, &loser, 200);
+    client.place_bet(&winner, &1, &300);
+    client.place_bet(&loser, &2, &200);
+    client.close_market(&oracle);
+
+    assert_eq!(client.try_cleanup(&oracle, &0, &10), Err(Ok(MarketError::NotResolved.into())));
+    client.resolve_market(&oracle, &1);
+    assert_eq!(client.try_cleanup(&oracle, &0, &10), Err(Ok(ExtMarketError::UnclaimedFunds.into())));
+
+    client.claim_payout(&winner);
+
+    // The first page leaves the livestream totals for the last one
+    assert_eq!(client.cleanup(&oracle, &0, &1), 1);
+    assert!(find_event(&env, "market_cleaned").is_some());
+    env.as_contract(&client.address, || {
+        let storage = env.storage().persistent();
+        assert!(!storage.has(&DataKey::Bets(winner.clone(), 1)));
+        assert!(storage.has(&DataKey::Bets(loser.clone(), 2)));
+        assert!(storage.has(&DataKey::TotalBets(1)));
+    });
+
+    assert_eq!(client.cleanup(&oracle, &1, &1), 1);
+    env.as_contract(&client.address, || {
+        let storage = env.storage().persistent();
+        assert!(!storage.has(&DataKey::Bets(loser.clone(), 2)));
+        assert!(!storage.has(&DataKey::TotalBets(1)));
+        assert!(!storage.has(&DataKey::TotalBets(2)));
+    });
+
+    let (ids, _, state, winning_id, total_pool, total_bettors, winning_pool) = client.get_market_info();
+    assert_eq!(ids, Vec::from_array(&env, [1u64, 2u64]));
+    assert_eq!(state, State::Resolved);
+    assert_eq!(winning_id, 1);
+    assert_eq!(total_pool, 500);
+    assert_eq!(total_bettors, 2);
+    assert_eq!(winning_pool, 300);
+}
+
+#[test]
+fn test_cleanup_pages_in_order() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let winner = Address::generate(&env);
+    let loser = Address::generate(&env);
+    mint(&env, &token, &winner, 300);
+    mint(&env, &token, &loser, 200);
+    client.place_bet(&winner, &1, &300);
+    client.place_bet(&loser, &2, &200);
+    client.close_market(&oracle);
+    client.resolve_market(&oracle, &1);
+
+    // Starting from the last page would clear the totals before the winner is checked
+    assert_eq!(client.try_cleanup(&oracle, &1, &1), Err(Ok(ExtMarketError::CleanupOutOfOrder.into())));
+    env.as_contract(&client.address, || {
+        assert!(env.storage().persistent().has(&DataKey::TotalBets(1)));
+    });
+
+    client.claim_payout(&winner);
+    assert_eq!(client.cleanup(&oracle, &0, &1), 1);
+    assert_eq!(client.try_cleanup(&oracle, &0, &1), Err(Ok(ExtMarketError::CleanupOutOfOrder.into())));
+    assert_eq!(client.cleanup(&oracle, &1, &1), 1);
+}
+
+#[test]
+fn test_max_imbalance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.max_imbalance_bps = 5000;
+    let (client, _, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    mint(&env, &token, &alice, 1000);
+    mint(&env, &token, &bob, 1000);
+
+    // The first stake is exempt since the other side is still empty
+    client.place_bet(&alice, &1, &100);
+    // The favorite may hold at most 150% of the underdog: 150 against 100
+    client.place_bet(&bob, &2, &150);
+    assert_eq!(
+        client.try_place_bet(&bob, &2, &1),
+        Err(Ok(ExtMarketError::BookUnbalanced.into()))
+    );
+
+    // Backing the underdog makes room on the favorite again
+    client.place_bet(&alice, &1, &100);
+    client.place_bet(&bob, &2, &150);
+    assert_eq!(client.get_user_bet(&bob, &2), 300);
+}
+
+#[test]
+fn test_max_imbalance_ignores_bonus() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let mut config = default_config(&env);
+    config.max_imbalance_bps = 5000;
+    let (client, _, token) = setup_market_with_config(&env, Vec::from_array(&env, [1u64, 2u64]), config);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let sponsor = Address::generate(&env);
+    mint(&env, &token, &alice, 1000);
+    mint(&env, &token, &bob, 1000);
+    mint(&env, &token, &sponsor, 1000);
+
+    client.place_bet(&alice, &1, &100);
+    client.add_bonus_pool(&sponsor, &1000);
+
+    // The bonus isn't a stake against livestream 2, so it's still capped at 150
+    client.place_bet(&bob, &2, &150);
+    assert_eq!(
+        client.try_place_bet(&bob, &2, &1),
+        Err(Ok(ExtMarketError::BookUnbalanced.into()))
+    );
+}
+
+#[test]
+fn test_get_recent_bets() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64]));
+
+    let user = Address::generate(&env);
+    mint(&env, &token, &user, 1000);
+    assert_eq!(client.get_recent_bets(&5).len(), 0);
+
+    for amount in 1..=25i128 {
+        env.ledger().with_mut(|li| li.timestamp += 10);
+        client.place_bet(&user, &1, &amount);
+    }
+
+    let recent = client.get_recent_bets(&100);
+    assert_eq!(recent.len(), 20);
+    let now = env.ledger().timestamp();
+    assert_eq!(recent.get(0).unwrap(), (user.clone(), 1, 25, now));
+    assert_eq!(recent.get(19).unwrap(), (user.clone(), 1, 6, now - 190));
+
+    let latest = client.get_recent_bets(&3);
+    assert_eq!(latest.len(), 3);
+    assert_eq!(latest.get(2).unwrap().2, 23);
+}
+
+#[test]
+fn test_claim_stats_weighted_counts_distinct_winners() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, oracle, token) = setup_market(&env, Vec::from_array(&env, [1u64, 2u64, 3u64]));
+
+    let hedger = Address::generate(&env);
+    let backer = Address::generate(&env);
+    let loser = Address::generate(&env);
+    mint(&env, &token, &hedger, 200);
+    mint(&env, &token, &backer, 100);
+    mint(&env, &token, &loser, 100);
+    client.place_bet(&hedger, &1, &100);
+    client.place_bet(&hedger, &2, &100);
+    client.place_bet(&backer, &2, &100);
+    client.place_bet(&loser, &3, &100);
+    client.close_market(&oracle);
+    client.resolve_weighted(&oracle, &Vec::from_array(&env, [1u64, 2u64]), &Vec::from_array(&env, [1u64, 1u64]));
+
+    // The hedger backed both paying livestreams, so they count twice until
+    // their single claim settles them as one winner
+    assert_eq!(client.get_claim_stats(), (0, 3));
+    client.claim_payout(&hedger);
+    assert_eq!(client.get_claim_stats(), (1, 2));
+    client.claim_payout(&backer);
+    assert_eq!(client.get_claim_stats(), (2, 2));
 }
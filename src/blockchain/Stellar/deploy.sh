@@ -139,5 +139,5 @@ echo ""
 echo -e "${YELLOW}⚠️  Next Steps:${NC}"
 echo "1. Update CONTRACTS.MarketFactory in contractsApi.ts with the new contract ID"
 echo "2. Initialize the contract using the 'Initialize Contract' button in the UI"
-echo "3. Or run: stellar contract invoke --id $CONTRACT_ID --network $NETWORK -- initialize --owner <YOUR_ADDRESS>"
+echo "3. Or run: stellar contract invoke --id $CONTRACT_ID --network $NETWORK -- initialize --deployer <YOUR_ADDRESS> --owner <OWNER_ADDRESS> --token <TOKEN_CONTRACT_ID>"
 echo ""
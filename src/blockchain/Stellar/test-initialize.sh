@@ -19,8 +19,12 @@ if [ -z "$OWNER_ADDRESS" ]; then
     exit 1
 fi
 
+# Markets take bets in native XLM
+TOKEN_ID=$(stellar contract id asset --asset native --network "$NETWORK")
+
 echo "Contract ID: $CONTRACT_ID"
 echo "Owner Address: $OWNER_ADDRESS"
+echo "Token ID: $TOKEN_ID"
 echo "Network: $NETWORK"
 echo ""
 
@@ -30,7 +34,7 @@ if stellar contract invoke \
     --id "$CONTRACT_ID" \
     --network "$NETWORK" \
     --source-account "$KEY_NAME" \
-    -- get_owner 2>&1 | grep -q "Contract not initialized\|UnreachableCodeReached"; then
+    -- get_owner 2>&1 | grep -q "Error(Contract, #2)\|UnreachableCodeReached"; then
     echo "✅ Contract is NOT initialized (as expected)"
     INITIALIZED=false
 else
@@ -65,7 +69,9 @@ if [ "$INITIALIZED" = "false" ]; then
         --network "$NETWORK" \
         --source-account "$KEY_NAME" \
         -- initialize \
-        --owner "$OWNER_ADDRESS" 2>&1)
+        --deployer "$OWNER_ADDRESS" \
+        --owner "$OWNER_ADDRESS" \
+        --token "$TOKEN_ID" 2>&1)
     
     if echo "$INIT_RESULT" | grep -q "success\|Success\|✅"; then
         echo "✅ Initialization successful!"
@@ -97,7 +103,7 @@ if echo "$VERIFY_RESULT" | grep -q "$OWNER_ADDRESS"; then
     echo "✅ SUCCESS! Contract is initialized"
     echo "Owner: $OWNER_ADDRESS"
     exit 0
-elif echo "$VERIFY_RESULT" | grep -q "Contract not initialized\|UnreachableCodeReached"; then
+elif echo "$VERIFY_RESULT" | grep -q "Error(Contract, #2)\|UnreachableCodeReached"; then
     echo "❌ Contract is still not initialized"
     echo "Result: $VERIFY_RESULT"
     exit 1